@@ -0,0 +1,31 @@
+/* C interface of image2unicodeart, built with `--features ffi`. */
+#ifndef I2UA_H
+#define I2UA_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Zero or NULL fields keep their defaults. */
+typedef struct I2uaOptions {
+    uint32_t width;
+    const char *charset;
+    const char *format; /* "text", "html", "svg" or "markdown" */
+    const char *color;  /* "none", "16", "256" or "truecolor" */
+} I2uaOptions;
+
+/* Return the art as a NUL-terminated UTF-8 string, or NULL on failure.
+   `options` may be NULL. Release results with i2ua_free_string. */
+char *i2ua_convert_bytes(const uint8_t *bytes, size_t len, const I2uaOptions *options);
+char *i2ua_convert_file(const char *path, const I2uaOptions *options);
+
+void i2ua_free_string(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
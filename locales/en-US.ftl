@@ -0,0 +1,43 @@
+# Messages printed by the command line tool. Translations override these by
+# pointing IMAGE2UNICODEART_MESSAGES at another .ftl file; any message id
+# missing there falls back to the English text below. Argument help can be
+# translated with `help-<argument>` ids (e.g. help-width) and `about`.
+
+error-invalid-input-path = Failed to open: { $input }
+error-failed-to-decode-input = Failed to decode input image!
+error-failed-to-write-to-output = Failed to save output to: { $output }
+error-failed-to-download = Failed to download: { $input }
+error-host-unreachable = Could not connect to the host (check the URL and network): { $input }
+error-download-rejected = The server refused the download (e.g. 404 Not Found): { $input }
+error-download-invalid = Invalid source: { $input }
+error-download-timed-out = Download timed out (see --timeout): { $input }
+error-download-too-large = Download exceeds the size limit (see --max-download-size): { $input }
+error-invalid-proxy = Invalid proxy: { $proxy }
+error-invalid-header = Invalid request header (expected "Name: value")
+error-invalid-data-uri = Invalid or non-image data: URI
+error-network-disabled = This build cannot download URLs: { $input }
+error-webhook-failed = Failed to post to webhook: { $url }
+error-failed-to-read-charset = Failed to read charset file: { $path }
+error-failed-to-read-link-map = Failed to read link map: { $path }
+error-invalid-link-map = Invalid link map: { $path }
+error-failed-to-read-palette = Failed to read palette: { $path }
+error-failed-to-read-tone-reference = Failed to read tone reference image: { $path }
+error-failed-to-read-watermark = Failed to read watermark image: { $path }
+error-invalid-palette = Invalid palette: { $path }
+error-invalid-layers = Invalid layers bundle: { $input }
+error-invalid-session = Invalid session script: { $path }
+error-empty-input = Input image has no pixels: { $input }
+error-invalid-output-width = Output width and height must be at least 1!
+error-empty-rect = --from-rect and --to-rect must overlap the image!
+error-output-too-large = The art is too large to convert; lower --width or --height!
+error-invalid-symbol-aspect-ratio = Symbol aspect ratio must be a positive number!
+error-invalid-tone-adjustment = Contrast must be a non-negative number, gamma a positive one and exposure finite!
+error-empty-charset = Charset must contain at least one glyph!
+error-image-too-large = Input image exceeds the decode limits (see --max-pixels): { $input }
+error-decoder-crashed = Decoder process failed on: { $input }
+error-decoder-timed-out = Decoder process took too long on: { $input }
+error-invalid-font = Failed to load font: { $path }
+error-missing-font = PNG output needs a font, pass one with --font!
+error-audio-capture-failed = Failed to capture audio from the default input device!
+error-visualizer-unavailable = This build does not include the audio visualizer (enable the "visualizer" feature)!
+error-viewer-unavailable = This build does not include the interactive viewer (enable the "viewer" feature)!
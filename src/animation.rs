@@ -0,0 +1,237 @@
+use std::{
+    io::{self, Cursor, Write},
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use image::{
+    codecs::gif::GifDecoder, AnimationDecoder, DynamicImage, GenericImageView, ImageDecoder,
+    ImageError, RgbaImage,
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{parallel, render, AsciiImage, ConversionOptions, DecodeLimits, ProgramError, Rect};
+
+pub const FRAMES_PER_SECOND: u32 = 20;
+
+// GIF frame delays below the minimum are replaced by the default, as browsers do
+const MIN_GIF_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_GIF_DELAY: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Animation {
+    Reveal,
+    Typewriter,
+    Dissolve,
+    KenBurns,
+}
+
+impl FromStr for Animation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reveal" => Ok(Self::Reveal),
+            "typewriter" => Ok(Self::Typewriter),
+            "dissolve" => Ok(Self::Dissolve),
+            "kenburns" => Ok(Self::KenBurns),
+            _ => Err(format!("unknown animation: {s}")),
+        }
+    }
+}
+
+pub(crate) fn frame_count(duration: Duration) -> usize {
+    ((duration.as_secs_f32() * FRAMES_PER_SECOND as f32).round() as usize).max(1)
+}
+
+pub(crate) fn frame_delay() -> Duration {
+    Duration::from_secs(1) / FRAMES_PER_SECOND
+}
+
+pub(crate) fn generate_frames(
+    image: &AsciiImage,
+    animation: Animation,
+    frame_count: usize,
+) -> Vec<AsciiImage> {
+    let w = image.dimensions.0 as usize;
+    let total = w * image.dimensions.1 as usize;
+
+    let mut order: Vec<usize> = (0..total).collect();
+    if animation == Animation::Dissolve {
+        shuffle(&mut order);
+    }
+
+    (1..=frame_count)
+        .map(|i| {
+            let mut visible = total * i / frame_count;
+            if animation == Animation::Reveal && w > 0 {
+                visible = (visible.div_ceil(w) * w).min(total);
+            }
+            let mut frame = image.clone();
+            for &idx in &order[visible..] {
+                let cell = &mut frame.data[idx / w][idx % w];
+                *cell = " ".repeat(cell.width().max(1));
+            }
+            frame
+        })
+        .collect()
+}
+
+pub(crate) fn ken_burns_frames(
+    img: &DynamicImage,
+    from: Rect,
+    to: Rect,
+    frame_count: usize,
+    convert: impl Fn(usize, &DynamicImage) -> AsciiImage + Sync + Send,
+) -> Vec<AsciiImage> {
+    // Every frame is resized to the grid of the whole image, so the rects take its
+    // aspect; interpolating between two rects of one aspect keeps it
+    let (w, h) = img.dimensions();
+    let aspect = w as f32 / h as f32;
+    let (from, to) = (
+        from.expand_to_aspect(aspect, (w, h)),
+        to.expand_to_aspect(aspect, (w, h)),
+    );
+    parallel::map_ordered((0..frame_count).collect(), |i| {
+        let t = if frame_count > 1 {
+            i as f32 / (frame_count - 1) as f32
+        } else {
+            1.0
+        };
+        // Smoothstep easing so the camera accelerates and settles gently
+        let rect = from.lerp(to, t * t * (3.0 - 2.0 * t));
+        convert(i, &img.crop_imm(rect.x, rect.y, rect.width, rect.height))
+    })
+}
+
+pub(crate) fn marquee_frames(image: &AsciiImage, viewport_width: u32) -> Vec<AsciiImage> {
+    let (w, h) = image.dimensions;
+    if w <= viewport_width {
+        return vec![image.clone()];
+    }
+
+    (0..w as usize)
+        .map(|offset| {
+            let mut frame = AsciiImage::create_empty((viewport_width, h));
+            for (y, line) in image.data.iter().enumerate() {
+                for x in 0..viewport_width as usize {
+                    let source = (offset + x) % line.len();
+                    frame.data[y][x].clone_from(&line[source]);
+                    frame.transparent[y][x] = image.transparent[y][source];
+                    frame.colors[y][x] = image.colors[y][source];
+                }
+            }
+            frame
+        })
+        .collect()
+}
+
+pub(crate) fn decode_gif_frames(
+    bytes: &[u8],
+    limits: DecodeLimits,
+) -> Result<Vec<(RgbaImage, Duration)>, ProgramError> {
+    let mut decoder =
+        GifDecoder::new(Cursor::new(bytes)).map_err(|_| ProgramError::FailedToDecodeInput)?;
+    let (w, h) = decoder.dimensions();
+    limits.check_dimensions(w, h)?;
+    decoder
+        .set_limits(limits.to_image_limits())
+        .map_err(|_| ProgramError::ImageTooLarge)?;
+    let frames = decoder
+        .into_frames()
+        .collect_frames()
+        .map_err(|err| match err {
+            ImageError::Limits(_) => ProgramError::ImageTooLarge,
+            _ => ProgramError::FailedToDecodeInput,
+        })?;
+    Ok(frames
+        .into_iter()
+        .map(|frame| {
+            let mut delay = Duration::from(frame.delay());
+            // Often 0, which would collapse the whole animation into one frame
+            if delay < MIN_GIF_DELAY {
+                delay = DEFAULT_GIF_DELAY;
+            }
+            (frame.into_buffer(), delay)
+        })
+        .collect())
+}
+
+// Samples the source frames at a fixed rate, optionally blending neighbouring
+// frames so low frame rate sources don't look like a slideshow
+pub(crate) fn resample_frames(
+    frames: &[(RgbaImage, Duration)],
+    fps: u32,
+    interpolate: bool,
+) -> Vec<RgbaImage> {
+    let total: Duration = frames.iter().map(|(_, delay)| *delay).sum();
+    let count = ((total.as_secs_f32() * fps as f32).round() as usize).max(1);
+
+    let mut result = Vec::with_capacity(count);
+    let mut current = 0;
+    let mut current_start = 0.0;
+    for i in 0..count {
+        let t = i as f32 / fps as f32;
+        while current + 1 < frames.len() && t >= current_start + frames[current].1.as_secs_f32() {
+            current_start += frames[current].1.as_secs_f32();
+            current += 1;
+        }
+
+        let (frame, delay) = &frames[current];
+        match frames.get(current + 1) {
+            Some((next, _)) if interpolate && !delay.is_zero() => {
+                let weight = ((t - current_start) / delay.as_secs_f32()).clamp(0.0, 1.0);
+                result.push(blend(frame, next, weight));
+            }
+            _ => result.push(frame.clone()),
+        }
+    }
+    result
+}
+
+fn blend(a: &RgbaImage, b: &RgbaImage, weight: f32) -> RgbaImage {
+    let mut blended = a.clone();
+    if a.dimensions() != b.dimensions() {
+        return blended;
+    }
+    for (out, other) in blended.pixels_mut().zip(b.pixels()) {
+        for c in 0..4 {
+            out[c] = (out[c] as f32 * (1.0 - weight) + other[c] as f32 * weight).round() as u8;
+        }
+    }
+    blended
+}
+
+// Renders every frame like a still image would be, so colors and the output format
+// apply during playback too
+pub(crate) fn play(
+    frames: &[AsciiImage],
+    frame_delay: Duration,
+    options: &ConversionOptions,
+) -> Result<(), ProgramError> {
+    let renderer = render::renderer(options)?;
+    let mut stdout = io::stdout().lock();
+    for (i, frame) in frames.iter().enumerate() {
+        if i > 0 {
+            // Move the cursor back to the top of the previous frame
+            let _ = write!(stdout, "\x1b[{}A", frame.dimensions.1);
+        }
+        let _ = stdout.write_all(&renderer.render(frame));
+        let _ = stdout.flush();
+        thread::sleep(frame_delay);
+    }
+    Ok(())
+}
+
+fn shuffle(order: &mut [usize]) {
+    // xorshift64, seeded with a constant so the dissolve pattern is reproducible
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    for i in (1..order.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let j = (state % (i as u64 + 1)) as usize;
+        order.swap(i, j);
+    }
+}
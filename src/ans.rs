@@ -0,0 +1,132 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::Rgb;
+use unicode_width::UnicodeWidthChar;
+
+use crate::{
+    color::{nearest_ansi16, ANSI16},
+    render::Renderer,
+    AsciiImage,
+};
+
+// Classic viewers assume an 80 column screen that wraps on its own, so a
+// full-width line must not be followed by a line break
+const SCREEN_COLUMNS: usize = 80;
+
+// Code page 437 bytes 0x80..=0xff
+const CP437_HIGH: &str = concat!(
+    "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»",
+    "░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀",
+    "αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}",
+);
+
+// Shades used in place of glyphs code page 437 has no equivalent for
+const SHADES: [u8; 5] = [b' ', 0xb0, 0xb1, 0xb2, 0xdb];
+
+pub(crate) struct AnsRenderer {
+    pub(crate) background: Rgb<u8>,
+}
+
+impl Renderer for AnsRenderer {
+    fn render(&self, art: &AsciiImage) -> Vec<u8> {
+        let background = nearest_ansi16(self.background, 8);
+        let mut ans = Vec::new();
+        let mut current = None;
+        let mut width = 0;
+        for (line, colors) in art.data.iter().zip(&art.colors) {
+            let mut columns = 0;
+            for (cell, &color) in line.iter().zip(colors) {
+                let foreground = nearest_ansi16(color, ANSI16.len());
+                if current != Some(foreground) {
+                    let bold = u8::from(foreground >= 8);
+                    ans.extend_from_slice(
+                        format!("\x1b[{bold};{};{}m", 30 + foreground % 8, 40 + background)
+                            .as_bytes(),
+                    );
+                    current = Some(foreground);
+                }
+                for c in cell.chars() {
+                    // Combining marks and control characters take no column
+                    let repeat = c.width().unwrap_or(0);
+                    if repeat == 0 {
+                        continue;
+                    }
+                    let byte = cp437_byte(c).unwrap_or_else(|| shade(color));
+                    ans.extend(std::iter::repeat(byte).take(repeat));
+                    columns += repeat;
+                }
+            }
+            if columns != SCREEN_COLUMNS {
+                ans.extend_from_slice(b"\r\n");
+            }
+            width = width.max(columns);
+        }
+        ans.extend_from_slice(b"\x1b[0m");
+
+        let file_size = ans.len() as u32;
+        // SAUCE records follow an end-of-file marker so DOS viewers stop before them
+        ans.push(0x1a);
+        ans.extend_from_slice(&sauce_record(
+            file_size,
+            width as u16,
+            art.data.len() as u16,
+        ));
+        ans
+    }
+}
+
+fn cp437_byte(c: char) -> Option<u8> {
+    if (' '..='~').contains(&c) {
+        return Some(c as u8);
+    }
+    CP437_HIGH
+        .chars()
+        .position(|high| high == c)
+        .map(|index| 0x80 + index as u8)
+}
+
+fn shade(Rgb([r, g, b]): Rgb<u8>) -> u8 {
+    let luminance = (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) / u8::MAX as f32;
+    SHADES[(luminance * (SHADES.len() - 1) as f32).round() as usize]
+}
+
+fn sauce_record(file_size: u32, width: u16, lines: u16) -> [u8; 128] {
+    let mut record = [0; 128];
+    record[..7].copy_from_slice(b"SAUCE00");
+    // Title, author and group are space padded
+    record[7..82].fill(b' ');
+    let title = b"image2unicodeart";
+    record[7..7 + title.len()].copy_from_slice(title);
+    record[82..90].copy_from_slice(current_date().as_bytes());
+    record[90..94].copy_from_slice(&file_size.to_le_bytes());
+    // Data type 1 (character), file type 1 (ANSi)
+    record[94] = 1;
+    record[95] = 1;
+    record[96..98].copy_from_slice(&width.to_le_bytes());
+    record[98..100].copy_from_slice(&lines.to_le_bytes());
+    let font = b"IBM VGA";
+    record[106..106 + font.len()].copy_from_slice(font);
+    record
+}
+
+// Today's date as CCYYMMDD, using Howard Hinnant's days-to-civil conversion
+fn current_date() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}{month:02}{day:02}")
+}
@@ -0,0 +1,70 @@
+use std::{
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use image::{imageops::FilterType, DynamicImage, GenericImageView};
+
+use crate::{output_dimensions, render, resize_and_convert, ConversionOptions};
+
+// Output widths tried, as fractions of the requested width, best quality first
+const WIDTH_STEPS: [f32; 5] = [1.0, 0.75, 0.5, 0.35, 0.25];
+
+const SAMPLE_RUNS: u32 = 3;
+
+// Share of the frame interval conversion and drawing may take, the rest is slack
+// for scheduling jitter
+const HEADROOM: f32 = 0.8;
+
+// Returns the highest-quality options (widest output first, then the sharpest
+// filter) whose frames convert and draw within the frame interval, or the
+// cheapest candidate when none does
+pub(crate) fn auto_tune<'a>(
+    sample: &DynamicImage,
+    options: &ConversionOptions<'a>,
+    fps: u32,
+) -> ConversionOptions<'a> {
+    let budget = Duration::from_secs(1).mul_f32(HEADROOM) / fps.max(1);
+    let columns = options.output_width.unwrap_or(sample.width());
+    let mut filters = vec![options.filter];
+    for filter in [FilterType::Triangle, FilterType::Nearest] {
+        if !filters.contains(&filter) {
+            filters.push(filter);
+        }
+    }
+
+    let mut candidate = options.clone();
+    for fraction in WIDTH_STEPS {
+        for &filter in &filters {
+            candidate = ConversionOptions {
+                output_width: Some(((columns as f32 * fraction) as u32).max(1)),
+                filter,
+                ..options.clone()
+            };
+            if frame_time(sample, &candidate) <= budget {
+                return candidate;
+            }
+        }
+    }
+    candidate
+}
+
+// Draws the sample like a playback frame, through the same renderer (color escapes
+// are most of the bytes in 256 and truecolor modes), and takes it back again, so the
+// terminal's throughput counts as well as the conversion
+fn frame_time(sample: &DynamicImage, options: &ConversionOptions) -> Duration {
+    // Playback reports a renderer that can't be built
+    let Ok(renderer) = render::renderer(options) else {
+        return Duration::ZERO;
+    };
+    let dimensions = output_dimensions(sample.dimensions(), options);
+    let mut stdout = io::stdout().lock();
+    let start = Instant::now();
+    for _ in 0..SAMPLE_RUNS {
+        let art = resize_and_convert(sample, dimensions, options);
+        let _ = stdout.write_all(&renderer.render(&art));
+        let _ = write!(stdout, "\x1b[{}A\x1b[J", dimensions.1);
+        let _ = stdout.flush();
+    }
+    start.elapsed() / SAMPLE_RUNS
+}
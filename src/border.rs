@@ -0,0 +1,127 @@
+use std::str::FromStr;
+
+use image::Rgb;
+use unicode_width::UnicodeWidthChar;
+
+use crate::AsciiImage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Border {
+    None,
+    Ascii,
+    Rounded,
+    Double,
+}
+
+impl FromStr for Border {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "ascii" => Ok(Self::Ascii),
+            "rounded" => Ok(Self::Rounded),
+            "double" => Ok(Self::Double),
+            _ => Err(format!("unknown border: {s}")),
+        }
+    }
+}
+
+impl Border {
+    // Top left, top right, bottom left and bottom right corners, then the horizontal
+    // and vertical edges
+    fn glyphs(self) -> Option<[char; 6]> {
+        match self {
+            Border::None => None,
+            Border::Ascii => Some(['+', '+', '+', '+', '-', '|']),
+            Border::Rounded => Some(['╭', '╮', '╰', '╯', '─', '│']),
+            Border::Double => Some(['╔', '╗', '╚', '╝', '═', '║']),
+        }
+    }
+}
+
+// Surrounds the art with a one cell wide frame in `color`, with the title (if any)
+// set into the top edge. Cells wider than one column keep the edges flush with the
+// art by padding towards it
+pub(crate) fn frame(
+    art: AsciiImage,
+    border: Border,
+    title: Option<&str>,
+    cell_width: u32,
+    color: Rgb<u8>,
+) -> AsciiImage {
+    let Some([top_left, top_right, bottom_left, bottom_right, horizontal, vertical]) =
+        border.glyphs()
+    else {
+        return art;
+    };
+    let (w, h) = art.dimensions;
+    let mut framed = AsciiImage::create_blank((w + 2, h + 2), cell_width);
+    framed.blit(&art, (1, 1));
+
+    let cell_width = cell_width as usize;
+    let run = |c: char, n: usize| c.to_string().repeat(n);
+    let (right, bottom) = (w as usize + 1, h as usize + 1);
+    let mut top = vec![run(horizontal, cell_width); w as usize];
+    if let Some(title) = title.filter(|_| w >= 2) {
+        // Starts one cell in and keeps the last cell an edge
+        let text = format!("{} {title} ", run(horizontal, cell_width));
+        top = into_cells(&text, cell_width, w as usize - 1, horizontal);
+        top.push(run(horizontal, cell_width));
+    }
+    framed.data[0][1..right].clone_from_slice(&top);
+    for x in 1..right {
+        framed.data[bottom][x] = run(horizontal, cell_width);
+    }
+    for y in 1..bottom {
+        framed.data[y][0] = format!("{vertical}{}", run(' ', cell_width - 1));
+        framed.data[y][right] = format!("{}{vertical}", run(' ', cell_width - 1));
+    }
+    framed.data[0][0] = format!("{top_left}{}", run(horizontal, cell_width - 1));
+    framed.data[0][right] = format!("{}{top_right}", run(horizontal, cell_width - 1));
+    framed.data[bottom][0] = format!("{bottom_left}{}", run(horizontal, cell_width - 1));
+    framed.data[bottom][right] = format!("{}{bottom_right}", run(horizontal, cell_width - 1));
+
+    for x in 0..=right {
+        framed.colors[0][x] = color;
+        framed.colors[bottom][x] = color;
+    }
+    for y in 0..=bottom {
+        framed.colors[y][0] = color;
+        framed.colors[y][right] = color;
+    }
+    framed
+}
+
+// Splits text into `count` cells of `cell_width` columns, padding a cell with `fill`
+// where the next character doesn't fit and filling the cells after the text with it
+fn into_cells(text: &str, cell_width: usize, count: usize, fill: char) -> Vec<String> {
+    let mut cells = text_cells(text, cell_width, fill);
+    cells.resize(count, fill.to_string().repeat(cell_width));
+    cells
+}
+
+// The cells of `cell_width` columns the text takes up, padded as above. Characters
+// wider than a cell are dropped
+pub(crate) fn text_cells(text: &str, cell_width: usize, fill: char) -> Vec<String> {
+    let mut cells = Vec::new();
+    let (mut cell, mut width) = (String::new(), 0);
+    for c in text.chars() {
+        let c_width = c.width().unwrap_or(0);
+        if c_width > cell_width {
+            continue;
+        }
+        if width + c_width > cell_width {
+            cell.extend(std::iter::repeat(fill).take(cell_width - width));
+            cells.push(std::mem::take(&mut cell));
+            width = 0;
+        }
+        cell.push(c);
+        width += c_width;
+    }
+    if !cell.is_empty() {
+        cell.extend(std::iter::repeat(fill).take(cell_width - width));
+        cells.push(cell);
+    }
+    cells
+}
@@ -0,0 +1,131 @@
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Fetched;
+
+// On-disk cache of downloaded images keyed by URL and revalidated with
+// ETag/Last-Modified. It's best effort: any cache I/O failure just means a download
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    pub dir: PathBuf,
+    // Least recently used entries are evicted beyond this many bytes
+    pub max_size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CacheEntry {
+    pub(crate) url: String,
+    pub(crate) etag: Option<String>,
+    pub(crate) last_modified: Option<String>,
+    pub(crate) content_type: Option<String>,
+}
+
+impl HttpCache {
+    pub fn new(dir: impl Into<PathBuf>, max_size: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            max_size,
+        }
+    }
+
+    // $XDG_CACHE_HOME, ~/.cache or %LOCALAPPDATA%, under "image2unicodeart"
+    pub fn default_dir() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))?;
+        Some(base.join(env!("CARGO_PKG_NAME")))
+    }
+
+    pub(crate) fn lookup(&self, url: &str) -> Option<CacheEntry> {
+        let meta = fs::read(self.path(url, "json")).ok()?;
+        let entry: CacheEntry = serde_json::from_slice(&meta).ok()?;
+        // Guards against (unlikely) key collisions
+        (entry.url == url).then_some(entry)
+    }
+
+    // The cached body of a revalidated entry, marked as recently used
+    pub(crate) fn load(&self, url: &str, entry: CacheEntry) -> Option<Fetched> {
+        let path = self.path(url, "body");
+        let bytes = fs::read(&path).ok()?;
+        let _ = File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        Some(Fetched {
+            bytes,
+            content_type: entry.content_type,
+        })
+    }
+
+    // Only responses carrying a validator are worth keeping
+    pub(crate) fn store(&self, entry: &CacheEntry, bytes: &[u8]) {
+        if (entry.etag.is_none() && entry.last_modified.is_none())
+            || bytes.len() as u64 > self.max_size
+        {
+            return;
+        }
+        let Ok(meta) = serde_json::to_vec(entry) else {
+            return;
+        };
+        let stored = fs::create_dir_all(&self.dir)
+            .and_then(|_| write_replacing(&self.path(&entry.url, "body"), bytes))
+            .and_then(|_| write_replacing(&self.path(&entry.url, "json"), &meta));
+        if stored.is_ok() {
+            self.evict();
+        }
+    }
+
+    fn evict(&self) {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut bodies: Vec<(SystemTime, u64, PathBuf)> = dir
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "body")
+            })
+            .filter_map(|path| {
+                let metadata = fs::metadata(&path).ok()?;
+                Some((metadata.modified().ok()?, metadata.len(), path))
+            })
+            .collect();
+        bodies.sort();
+
+        let mut total: u64 = bodies.iter().map(|(_, size, _)| size).sum();
+        for (_, size, path) in bodies {
+            if total <= self.max_size {
+                break;
+            }
+            let _ = fs::remove_file(path.with_extension("json"));
+            if fs::remove_file(&path).is_ok() {
+                total -= size;
+            }
+        }
+    }
+
+    fn path(&self, url: &str, extension: &str) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}.{extension}", fnv1a(url.as_bytes())))
+    }
+}
+
+// Readers never see a half written file
+fn write_replacing(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&temp, bytes).and_then(|_| fs::rename(&temp, path))
+}
+
+// Stable across builds and platforms, unlike std's hashers
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -0,0 +1,149 @@
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{charset::is_weighted_spec, charset_preset, ProgramError};
+
+const CALIBRATION_SCALE: f32 = 64.0;
+
+// Density difference below which two glyphs count as equally dense
+const DENSITY_TOLERANCE: f32 = 0.02;
+
+// Rasterizes every glyph with the given font and returns a weighted charset
+// specification ordered from the least to the most ink coverage
+pub fn calibrate_charset(charset: &str, font_data: &[u8]) -> Result<String, ProgramError> {
+    let font = FontRef::try_from_slice(font_data).map_err(|_| ProgramError::InvalidFont)?;
+    let coverage = glyph_coverage(charset, &font);
+
+    let min = coverage.iter().map(|(_, c)| *c).fold(f32::MAX, f32::min);
+    let max = coverage.iter().map(|(_, c)| *c).fold(f32::MIN, f32::max);
+    let range = max - min;
+    let last = (coverage.len().max(2) - 1) as f32;
+
+    let mut weighted: Vec<(&str, f32)> = coverage
+        .into_iter()
+        .enumerate()
+        .map(|(i, (glyph, c))| {
+            let weight = if range > f32::EPSILON {
+                (c - min) / range
+            } else {
+                i as f32 / last
+            };
+            (glyph, weight)
+        })
+        .collect();
+    weighted.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+
+    let entries: Vec<String> = weighted
+        .iter()
+        .map(|(glyph, weight)| format!("{glyph}:{weight:.3}"))
+        .collect();
+    Ok(entries.join(","))
+}
+
+pub fn calibrate_charset_file(charset: &str, font_path: &str) -> Result<String, ProgramError> {
+    let font_data = std::fs::read(font_path).map_err(|_| ProgramError::InvalidFont)?;
+    calibrate_charset(charset, &font_data)
+}
+
+pub(crate) fn glyph_coverage<'c>(charset: &'c str, font: &impl Font) -> Vec<(&'c str, f32)> {
+    let scale = PxScale::from(CALIBRATION_SCALE);
+    let scaled = font.as_scaled(scale);
+    let cell_area = scaled.h_advance(font.glyph_id('M')) * scaled.height();
+
+    charset
+        .graphemes(true)
+        .map(|glyph| {
+            let mut ink = 0.0;
+            for c in glyph.chars() {
+                let positioned = font
+                    .glyph_id(c)
+                    .with_scale_and_position(scale, point(0.0, scaled.ascent()));
+                if let Some(outlined) = font.outline_glyph(positioned) {
+                    outlined.draw(|_, _, coverage| ink += coverage);
+                }
+            }
+            (glyph, ink / cell_area.max(1.0))
+        })
+        .collect()
+}
+
+// Reorders a plain ramp from the least to the most ink, measured with the font when
+// one is given and estimated otherwise. Glyphs of unknown density keep their place,
+// and weighted specifications are returned as is since their weights set the order
+pub fn sort_charset(charset: &str, font_data: Option<&[u8]>) -> String {
+    if is_weighted_spec(charset) {
+        return charset.to_string();
+    }
+    let densities = densities(charset, font_data);
+    let mut known: Vec<(&str, f32)> = densities
+        .iter()
+        .filter_map(|(glyph, density)| Some((*glyph, (*density)?)))
+        .collect();
+    known.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+    let mut known = known.into_iter();
+    densities
+        .iter()
+        .map(|(glyph, density)| match density {
+            Some(_) => known.next().map_or(*glyph, |(glyph, _)| glyph),
+            None => *glyph,
+        })
+        .collect()
+}
+
+// Share (0..1) of the pairs of glyphs with a known density that a plain ramp puts in
+// the wrong order
+pub(crate) fn density_disorder(charset: &str, font_data: Option<&[u8]>) -> f32 {
+    if is_weighted_spec(charset) {
+        return 0.0;
+    }
+    let known: Vec<f32> = densities(charset, font_data)
+        .into_iter()
+        .filter_map(|(_, density)| density)
+        .collect();
+    let (mut pairs, mut inverted) = (0, 0);
+    for (i, a) in known.iter().enumerate() {
+        for b in &known[i + 1..] {
+            if (a - b).abs() < DENSITY_TOLERANCE {
+                continue;
+            }
+            pairs += 1;
+            inverted += (a > b) as u32;
+        }
+    }
+    inverted as f32 / pairs.max(1) as f32
+}
+
+fn densities<'c>(charset: &'c str, font_data: Option<&[u8]>) -> Vec<(&'c str, Option<f32>)> {
+    match font_data.and_then(|data| FontRef::try_from_slice(data).ok()) {
+        Some(font) => glyph_coverage(charset, &font)
+            .into_iter()
+            .map(|(glyph, coverage)| (glyph, Some(coverage)))
+            .collect(),
+        None => charset
+            .graphemes(true)
+            .map(|glyph| (glyph, estimated_density(glyph)))
+            .collect(),
+    }
+}
+
+// Rough coverage without a font to measure: block elements and braille dots are
+// counted, other glyphs ranked by the ascii-extended preset, which is ordered by ink
+fn estimated_density(glyph: &str) -> Option<f32> {
+    let mut chars = glyph.chars();
+    let (Some(c), None) = (chars.next(), chars.next()) else {
+        return None;
+    };
+    match c {
+        ' ' => Some(0.0),
+        '░' => Some(0.25),
+        '▒' | '▀' | '▄' | '▌' | '▐' => Some(0.5),
+        '▓' => Some(0.75),
+        '█' => Some(1.0),
+        '\u{2800}'..='\u{28ff}' => Some((c as u32 - 0x2800).count_ones() as f32 / 8.0),
+        _ => {
+            let ramp = charset_preset("ascii-extended")?;
+            let rank = ramp.chars().position(|r| r == c)?;
+            Some(rank as f32 / (ramp.chars().count() - 1) as f32)
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use image::ImageFormat;
+
+use crate::{CHARSET_PRESETS, PALETTE_PRESETS};
+
+// JSON description of what this build supports, for wrapper scripts and GUIs
+pub fn capabilities_json() -> String {
+    let mut input_formats: Vec<String> = ImageFormat::all()
+        .filter(|format| format.reading_enabled())
+        .map(|format| json_string(&format!("{format:?}").to_lowercase()))
+        .collect();
+    if cfg!(feature = "raw") {
+        input_formats.push(json_string("raw"));
+    }
+    if cfg!(feature = "svg") {
+        input_formats.push(json_string("svg"));
+    }
+    let presets: Vec<String> = CHARSET_PRESETS
+        .iter()
+        .map(|(name, _)| json_string(name))
+        .collect();
+    let palettes: Vec<String> = PALETTE_PRESETS
+        .iter()
+        .map(|(name, _)| json_string(name))
+        .collect();
+    let animations = ["reveal", "typewriter", "dissolve", "kenburns", "marquee"].map(json_string);
+
+    format!(
+        concat!(
+            "{{",
+            "\"version\":{},",
+            "\"inputs\":[\"file\",\"url\",\"data\",\"stdin\",\"generate\"],",
+            "\"input_formats\":[{}],",
+            "\"output_formats\":[\"text\",\"html\",\"svg\",\"png\",\"ans\",\"markdown\",\"layers\",\"discord-webhook\",\"slack-webhook\"],",
+            "\"cell_modes\":[\"glyphs\",\"emoji\",\"halfblock\",\"braille\",\"octant\",\"adaptive\"],",
+            "\"color_modes\":[\"none\",\"16\",\"256\",\"truecolor\"],",
+            "\"animations\":[{}],",
+            "\"charset_presets\":[{}],",
+            "\"palette_presets\":[{}],",
+            "\"generators\":[\"plasma\",\"life\",\"fire\"],",
+            "\"features\":{{\"async\":{},\"decode_isolation\":true,\"ffi\":{},\"font_calibration\":true,\"formats\":{},\"glyph_matching\":true,\"http\":{},\"link_maps\":true,\"localization\":true,\"parallel\":{},\"python\":{},\"raw\":{},\"svg\":{},\"viewer\":{},\"visualizer\":{},\"wasm\":{}}}",
+            "}}"
+        ),
+        json_string(env!("CARGO_PKG_VERSION")),
+        input_formats.join(","),
+        animations.join(","),
+        presets.join(","),
+        palettes.join(","),
+        cfg!(feature = "async"),
+        cfg!(feature = "ffi"),
+        cfg!(feature = "formats"),
+        cfg!(feature = "http"),
+        cfg!(feature = "parallel"),
+        cfg!(feature = "python"),
+        cfg!(feature = "raw"),
+        cfg!(feature = "svg"),
+        cfg!(feature = "viewer"),
+        cfg!(feature = "visualizer"),
+        cfg!(feature = "wasm"),
+    )
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
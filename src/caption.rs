@@ -0,0 +1,92 @@
+use std::str::FromStr;
+
+use image::Rgb;
+
+use crate::{border, AsciiImage};
+
+// Where the caption sits on the art, as vertical then horizontal placement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptionPosition {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    CenterLeft,
+    Center,
+    CenterRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+impl FromStr for CaptionPosition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top-left" => Ok(Self::TopLeft),
+            "top-center" => Ok(Self::TopCenter),
+            "top-right" => Ok(Self::TopRight),
+            "center-left" => Ok(Self::CenterLeft),
+            "center" => Ok(Self::Center),
+            "center-right" => Ok(Self::CenterRight),
+            "bottom-left" => Ok(Self::BottomLeft),
+            "bottom-center" => Ok(Self::BottomCenter),
+            "bottom-right" => Ok(Self::BottomRight),
+            _ => Err(format!("unknown caption position: {s}")),
+        }
+    }
+}
+
+impl CaptionPosition {
+    // Where the caption sits along each axis, 0 (start) to 1 (end)
+    fn anchor(self) -> (f32, f32) {
+        match self {
+            CaptionPosition::TopLeft => (0.0, 0.0),
+            CaptionPosition::TopCenter => (0.5, 0.0),
+            CaptionPosition::TopRight => (1.0, 0.0),
+            CaptionPosition::CenterLeft => (0.0, 0.5),
+            CaptionPosition::Center => (0.5, 0.5),
+            CaptionPosition::CenterRight => (1.0, 0.5),
+            CaptionPosition::BottomLeft => (0.0, 1.0),
+            CaptionPosition::BottomCenter => (0.5, 1.0),
+            CaptionPosition::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Caption<'a> {
+    // One row of the art per line
+    pub text: &'a str,
+    pub position: CaptionPosition,
+    // Defaults to the color that stands out against the background
+    pub color: Option<Rgb<u8>>,
+}
+
+// Writes the caption over the art's glyphs, each line cut to the art's width
+pub(crate) fn overlay(
+    mut art: AsciiImage,
+    caption: Option<&Caption>,
+    cell_width: u32,
+    ink: Rgb<u8>,
+) -> AsciiImage {
+    let Some(caption) = caption else {
+        return art;
+    };
+    let (w, h) = art.dimensions;
+    let lines: Vec<Vec<String>> = caption
+        .text
+        .lines()
+        .take(h as usize)
+        .map(|line| border::text_cells(line, cell_width as usize, ' '))
+        .collect();
+    let (ax, ay) = caption.position.anchor();
+    let top = ((h as usize - lines.len()) as f32 * ay).round() as u32;
+    let color = caption.color.unwrap_or(ink);
+    for (y, cells) in (top..).zip(&lines) {
+        let cells = &cells[..cells.len().min(w as usize)];
+        let x = ((w as usize - cells.len()) as f32 * ax).round() as u32;
+        art.overlay(cells, (x, y), color);
+    }
+    art
+}
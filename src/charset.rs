@@ -0,0 +1,157 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::ProgramError;
+
+pub const CHARSET_PRESETS: &[(&str, &str)] = &[
+    ("blocks", " ░▒▓█"),
+    ("ascii", " .:-=+*#%@"),
+    (
+        "ascii-extended",
+        " .'`^\",:;Il!i><~+_-?][}{1)(|\\/tfjrxnuvczXYUJCLQ0OZmwqpdbkhao*#MW&8%B@$",
+    ),
+    ("dots", " ⠁⠃⠇⡇⡏⡟⡿⣿"),
+    ("binary", " █"),
+];
+
+pub fn charset_preset(name: &str) -> Option<&'static str> {
+    CHARSET_PRESETS
+        .iter()
+        .find(|(preset, _)| *preset == name)
+        .map(|(_, charset)| *charset)
+}
+
+// Brightness levels of the precomputed glyph lookup
+const LOOKUP_LEVELS: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Charset {
+    glyphs: Vec<String>,
+    weights: Vec<f32>,
+    cell_width: usize,
+    mixed_widths: bool,
+    // Index of the nearest glyph for every quantized brightness
+    lookup: Vec<usize>,
+}
+
+impl Charset {
+    // Accepts either a plain ramp (" .:#") with evenly spaced glyphs or a weighted
+    // specification (".:0.05,+:0.3,#:0.8") where every glyph carries its coverage
+    pub fn parse(spec: &str) -> Self {
+        Self::parse_weighted(spec).unwrap_or_else(|| Self::uniform(spec))
+    }
+
+    pub fn uniform(ramp: &str) -> Self {
+        let glyphs: Vec<String> = ramp.graphemes(true).map(String::from).collect();
+        let n = glyphs.len() as f32;
+        let weights = (0..glyphs.len()).map(|i| (i as f32 + 0.5) / n).collect();
+        Self::with_weights(glyphs, weights)
+    }
+
+    // Glyphs narrower than the widest one are padded with spaces so every cell
+    // spans the same number of terminal columns
+    fn with_weights(mut glyphs: Vec<String>, weights: Vec<f32>) -> Self {
+        let widths: Vec<usize> = glyphs.iter().map(|g| display_width(g)).collect();
+        let cell_width = widths.iter().copied().max().unwrap_or(1);
+        let mixed_widths = widths.iter().any(|&w| w != cell_width);
+        for (glyph, width) in glyphs.iter_mut().zip(widths) {
+            glyph.extend(std::iter::repeat(' ').take(cell_width - width));
+        }
+        let lookup = (0..LOOKUP_LEVELS)
+            .map(|level| nearest_weight(&weights, level as f32 / (LOOKUP_LEVELS - 1) as f32))
+            .collect();
+        Self {
+            glyphs,
+            weights,
+            cell_width,
+            mixed_widths,
+            lookup,
+        }
+    }
+
+    fn parse_weighted(spec: &str) -> Option<Self> {
+        let mut glyphs = Vec::new();
+        let mut weights = Vec::new();
+        let mut rest = spec;
+        loop {
+            // The glyph is always a single grapheme, so ',' and ':' can be glyphs too
+            let glyph = rest.graphemes(true).next()?;
+            rest = rest[glyph.len()..].strip_prefix(':')?;
+            let (weight, tail) = match rest.split_once(',') {
+                Some((weight, tail)) => (weight, Some(tail)),
+                None => (rest, None),
+            };
+            glyphs.push(glyph.to_string());
+            weights.push(weight.trim().parse().ok()?);
+            match tail {
+                Some(tail) => rest = tail,
+                None => break,
+            }
+        }
+        Some(Self::with_weights(glyphs, weights))
+    }
+
+    pub fn glyphs(&self) -> &[String] {
+        &self.glyphs
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    pub fn cell_width(&self) -> usize {
+        self.cell_width
+    }
+
+    pub fn has_mixed_widths(&self) -> bool {
+        self.mixed_widths
+    }
+
+    pub fn pad(&self, glyph: &str) -> String {
+        let padding = self.cell_width.saturating_sub(display_width(glyph));
+        let mut padded = glyph.to_string();
+        padded.extend(std::iter::repeat(' ').take(padding));
+        padded
+    }
+
+    pub fn glyph_for(&self, brightness: f32) -> &str {
+        let level = (brightness.clamp(0.0, 1.0) * (LOOKUP_LEVELS - 1) as f32).round() as usize;
+        self.lookup
+            .get(level)
+            .and_then(|&index| self.glyphs.get(index))
+            .map_or(" ", String::as_str)
+    }
+}
+
+pub(crate) fn is_weighted_spec(spec: &str) -> bool {
+    Charset::parse_weighted(spec).is_some()
+}
+
+fn nearest_weight(weights: &[f32], brightness: f32) -> usize {
+    weights
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| (*a - brightness).abs().total_cmp(&(*b - brightness).abs()))
+        .map_or(0, |(index, _)| index)
+}
+
+fn display_width(glyph: &str) -> usize {
+    glyph.width().max(1)
+}
+
+// Reads a ramp either from a single line or from one glyph (or "glyph:weight") per line
+pub fn read_charset_file(path: &str) -> Result<String, ProgramError> {
+    let content = std::fs::read_to_string(path).map_err(|_| ProgramError::FailedToReadCharset)?;
+    let content = content.strip_prefix('\u{feff}').unwrap_or(&content);
+    let lines: Vec<&str> = content
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    if lines.len() > 1 && lines.iter().all(|line| line.contains(':')) {
+        Ok(lines.join(","))
+    } else {
+        Ok(lines.concat())
+    }
+}
@@ -0,0 +1,244 @@
+use std::{str::FromStr, sync::OnceLock};
+
+use image::Rgb;
+
+use crate::{parse_color, ProgramError};
+
+// VGA palette in SGR order; indices 8.. are the bright variants
+pub(crate) const ANSI16: [[u8; 3]; 16] = [
+    [0x00, 0x00, 0x00],
+    [0xaa, 0x00, 0x00],
+    [0x00, 0xaa, 0x00],
+    [0xaa, 0x55, 0x00],
+    [0x00, 0x00, 0xaa],
+    [0xaa, 0x00, 0xaa],
+    [0x00, 0xaa, 0xaa],
+    [0xaa, 0xaa, 0xaa],
+    [0x55, 0x55, 0x55],
+    [0xff, 0x55, 0x55],
+    [0x55, 0xff, 0x55],
+    [0xff, 0xff, 0x55],
+    [0x55, 0x55, 0xff],
+    [0xff, 0x55, 0xff],
+    [0x55, 0xff, 0xff],
+    [0xff, 0xff, 0xff],
+];
+
+pub const PALETTE_PRESETS: &[(&str, &str)] = &[
+    (
+        "gruvbox",
+        "#282828 #cc241d #98971a #d79921 #458588 #b16286 #689d6a #a89984 \
+         #928374 #fb4934 #b8bb26 #fabd2f #83a598 #d3869b #8ec07c #ebdbb2",
+    ),
+    (
+        "solarized",
+        "#002b36 #073642 #586e75 #657b83 #839496 #93a1a1 #eee8d5 #fdf6e3 \
+         #b58900 #cb4b16 #dc322f #d33682 #6c71c4 #268bd2 #2aa198 #859900",
+    ),
+    (
+        "nord",
+        "#2e3440 #3b4252 #434c5e #4c566a #d8dee9 #e5e9f0 #eceff4 #8fbcbb \
+         #88c0d0 #81a1c1 #5e81ac #bf616a #d08770 #ebcb8b #a3be8c #b48ead",
+    ),
+    (
+        "dracula",
+        "#282a36 #44475a #f8f8f2 #6272a4 #8be9fd #50fa7b #ffb86c #ff79c6 \
+         #bd93f9 #ff5555 #f1fa8c",
+    ),
+    ("gameboy", "#0f380f #306230 #8bac0f #9bbc0f"),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    None,
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" | "off" => Ok(Self::None),
+            "16" => Ok(Self::Ansi16),
+            "256" => Ok(Self::Ansi256),
+            "truecolor" | "24bit" => Ok(Self::TrueColor),
+            _ => Err(format!("unknown color mode: {s}")),
+        }
+    }
+}
+
+impl ColorMode {
+    // Upper bound of the escape sequence bytes added per cell
+    pub(crate) fn sgr_len_hint(self) -> usize {
+        match self {
+            ColorMode::None => 0,
+            ColorMode::Ansi16 => "\x1b[97m".len(),
+            ColorMode::Ansi256 => "\x1b[38;5;255m".len(),
+            ColorMode::TrueColor => "\x1b[38;2;255;255;255m".len(),
+        }
+    }
+
+    // SGR parameters selecting `color` as the foreground, None when uncolored
+    pub(crate) fn foreground_sgr(self, color: Rgb<u8>) -> Option<String> {
+        self.sgr(color, 0)
+    }
+
+    pub(crate) fn background_sgr(self, color: Rgb<u8>) -> Option<String> {
+        self.sgr(color, 10)
+    }
+
+    // The color a terminal in this mode shows for `color`, None when uncolored, so
+    // markup and image exports match the text output
+    pub(crate) fn displayed(self, color: Rgb<u8>) -> Option<Rgb<u8>> {
+        match self {
+            ColorMode::None => None,
+            ColorMode::Ansi16 => Some(Rgb(ANSI16[nearest_ansi16(color, ANSI16.len())])),
+            ColorMode::Ansi256 => Some(Rgb(xterm256(nearest_xterm256(color)))),
+            ColorMode::TrueColor => Some(color),
+        }
+    }
+
+    // Background codes are the foreground ones plus 10
+    fn sgr(self, color: Rgb<u8>, offset: u8) -> Option<String> {
+        match self {
+            ColorMode::None => None,
+            ColorMode::Ansi16 => {
+                let index = nearest_ansi16(color, ANSI16.len()) as u8;
+                Some(if index < 8 {
+                    (30 + offset + index).to_string()
+                } else {
+                    (90 + offset + index - 8).to_string()
+                })
+            }
+            ColorMode::Ansi256 => Some(format!("{};5;{}", 38 + offset, nearest_xterm256(color))),
+            ColorMode::TrueColor => {
+                let Rgb([r, g, b]) = color;
+                Some(format!("{};2;{r};{g};{b}", 38 + offset))
+            }
+        }
+    }
+}
+
+// A fixed set of colors that color output is snapped to
+#[derive(Debug, Clone)]
+pub struct Palette {
+    colors: Vec<Rgb<u8>>,
+    lab: Vec<[f32; 3]>,
+}
+
+impl Palette {
+    pub fn new(colors: Vec<Rgb<u8>>) -> Self {
+        let lab = colors.iter().map(|&color| srgb_to_lab(color)).collect();
+        Self { colors, lab }
+    }
+
+    // Colors separated by whitespace or commas, as names or #rgb/#rrggbb
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let colors = spec
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .map(parse_color)
+            .collect::<Result<Vec<_>, _>>()?;
+        if colors.is_empty() {
+            return Err("palette has no colors".to_string());
+        }
+        Ok(Self::new(colors))
+    }
+
+    pub fn colors(&self) -> &[Rgb<u8>] {
+        &self.colors
+    }
+
+    pub fn nearest(&self, color: Rgb<u8>) -> Rgb<u8> {
+        self.colors[nearest(color, &self.lab)]
+    }
+}
+
+// Loads a named preset or, failing that, a palette file
+pub fn load_palette(name_or_path: &str) -> Result<Palette, ProgramError> {
+    let spec = match PALETTE_PRESETS
+        .iter()
+        .find(|(name, _)| *name == name_or_path)
+    {
+        Some((_, colors)) => colors.to_string(),
+        None => {
+            std::fs::read_to_string(name_or_path).map_err(|_| ProgramError::FailedToReadPalette)?
+        }
+    };
+    Palette::parse(&spec).map_err(|_| ProgramError::InvalidPalette)
+}
+
+// Nearest of the first `count` ANSI16 colors, so callers limited to the eight
+// base colors (e.g. backgrounds) can share the table
+pub(crate) fn nearest_ansi16(color: Rgb<u8>, count: usize) -> usize {
+    static LAB: OnceLock<Vec<[f32; 3]>> = OnceLock::new();
+    let palette = LAB.get_or_init(|| ANSI16.iter().map(|&rgb| srgb_to_lab(Rgb(rgb))).collect());
+    nearest(color, &palette[..count])
+}
+
+pub(crate) fn nearest_xterm256(color: Rgb<u8>) -> usize {
+    static LAB: OnceLock<Vec<[f32; 3]>> = OnceLock::new();
+    let palette = LAB.get_or_init(|| {
+        (0..256)
+            .map(|index| srgb_to_lab(Rgb(xterm256(index))))
+            .collect()
+    });
+    nearest(color, palette)
+}
+
+pub(crate) fn xterm256(index: usize) -> [u8; 3] {
+    match index {
+        0..=15 => ANSI16[index],
+        16..=231 => {
+            let cube = index - 16;
+            [cube / 36, cube / 6 % 6, cube % 6].map(|level| CUBE_LEVELS[level])
+        }
+        _ => [(8 + (index - 232) * 10) as u8; 3],
+    }
+}
+
+// Euclidean distance in CIELAB tracks perceived difference far better than in
+// sRGB, which visibly shifts skin tones and blues towards the wrong entries
+pub(crate) fn nearest(color: Rgb<u8>, palette: &[[f32; 3]]) -> usize {
+    let lab = srgb_to_lab(color);
+    palette
+        .iter()
+        .map(|entry| {
+            entry
+                .iter()
+                .zip(lab)
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum::<f32>()
+        })
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map_or(0, |(index, _)| index)
+}
+
+pub(crate) fn srgb_to_lab(Rgb(rgb): Rgb<u8>) -> [f32; 3] {
+    let [r, g, b] = rgb.map(|c| {
+        let c = c as f32 / u8::MAX as f32;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    // Linear sRGB to XYZ, normalized by the D65 white point
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.950_47;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.088_83;
+    let [fx, fy, fz] = [x, y, z].map(|t| {
+        if t > 216.0 / 24_389.0 {
+            t.cbrt()
+        } else {
+            (24_389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    });
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
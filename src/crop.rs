@@ -0,0 +1,172 @@
+use std::str::FromStr;
+
+use image::{DynamicImage, GenericImageView};
+
+use crate::{terminal_height, terminal_width, ConversionOptions, Rect};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Length {
+    Pixels(u32),
+    // Share (0..100) of the image's width or height
+    Percent(f32),
+}
+
+impl Length {
+    fn resolve(self, size: u32) -> u32 {
+        match self {
+            Length::Pixels(pixels) => pixels,
+            Length::Percent(percent) => (size as f32 * percent.clamp(0.0, 100.0) / 100.0) as u32,
+        }
+    }
+}
+
+impl FromStr for Length {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.strip_suffix('%') {
+            Some(percent) => percent
+                .trim()
+                .parse()
+                .ok()
+                .filter(|percent: &f32| percent.is_finite())
+                .map(Length::Percent)
+                .ok_or(()),
+            None => s.parse().map(Length::Pixels).map_err(|_| ()),
+        }
+    }
+}
+
+// Region of the source image to convert, each value in pixels or percent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crop {
+    pub x: Length,
+    pub y: Length,
+    pub width: Length,
+    pub height: Length,
+}
+
+impl Crop {
+    fn resolve(self, (w, h): (u32, u32)) -> Rect {
+        Rect {
+            x: self.x.resolve(w),
+            y: self.y.resolve(h),
+            width: self.width.resolve(w),
+            height: self.height.resolve(h),
+        }
+        .clamp_to((w, h))
+    }
+}
+
+impl FromStr for Crop {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<Length> = s
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("invalid crop: {s}"))?;
+        match values[..] {
+            [x, y, width, height] => Ok(Crop {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(format!(
+                "invalid crop (expected x,y,w,h in pixels or %): {s}"
+            )),
+        }
+    }
+}
+
+// Which part of the image a cover crop keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl FromStr for Focus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "center" => Ok(Self::Center),
+            "top" => Ok(Self::Top),
+            "bottom" => Ok(Self::Bottom),
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            "top-left" => Ok(Self::TopLeft),
+            "top-right" => Ok(Self::TopRight),
+            "bottom-left" => Ok(Self::BottomLeft),
+            "bottom-right" => Ok(Self::BottomRight),
+            _ => Err(format!("unknown focus: {s}")),
+        }
+    }
+}
+
+impl Focus {
+    // Where the kept region sits along each axis, 0 (start) to 1 (end)
+    pub(crate) fn anchor(self) -> (f32, f32) {
+        match self {
+            Focus::Center => (0.5, 0.5),
+            Focus::Top => (0.5, 0.0),
+            Focus::Bottom => (0.5, 1.0),
+            Focus::Left => (0.0, 0.5),
+            Focus::Right => (1.0, 0.5),
+            Focus::TopLeft => (0.0, 0.0),
+            Focus::TopRight => (1.0, 0.0),
+            Focus::BottomLeft => (0.0, 1.0),
+            Focus::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+// Applies the crop, then cuts the result down to fill the output area (--width or
+// the terminal's columns by the terminal's rows, less one for the prompt) around
+// the focus, like CSS's object-fit: cover
+pub(crate) fn crop_input(
+    img: DynamicImage,
+    crop: Option<Crop>,
+    focus: Option<Focus>,
+    options: &ConversionOptions,
+) -> DynamicImage {
+    let img = match crop {
+        Some(crop) => {
+            let region = crop.resolve(img.dimensions());
+            img.crop_imm(region.x, region.y, region.width, region.height)
+        }
+        None => img,
+    };
+    let (w, h) = img.dimensions();
+    // Empty images are reported by validation
+    let Some(focus) = focus.filter(|_| w > 0 && h > 0) else {
+        return img;
+    };
+
+    let cell_width = options.cell_width();
+    let columns = options.output_width.unwrap_or_else(terminal_width) / cell_width;
+    let rows = terminal_height().saturating_sub(1).max(1);
+    // Source width / height that fills `columns` x `rows` cells
+    let aspect =
+        columns.max(1) as f32 * options.symbol_aspect_ratio * cell_width as f32 / rows as f32;
+    let (crop_w, crop_h) = if w as f32 / h as f32 > aspect {
+        (((h as f32 * aspect) as u32).clamp(1, w), h)
+    } else {
+        (w, ((w as f32 / aspect) as u32).clamp(1, h))
+    };
+    let (ax, ay) = focus.anchor();
+    let x = ((w - crop_w) as f32 * ax).round() as u32;
+    let y = ((h - crop_h) as f32 * ay).round() as u32;
+    img.crop_imm(x, y, crop_w, crop_h)
+}
@@ -0,0 +1,50 @@
+use std::str::FromStr;
+
+use crate::temporal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dither {
+    None,
+    Ordered,
+    // Blue noise rotated per frame, with glyphs persisting in static areas
+    Temporal,
+}
+
+impl FromStr for Dither {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" | "off" => Ok(Self::None),
+            "ordered" | "bayer" => Ok(Self::Ordered),
+            "temporal" | "blue-noise" => Ok(Self::Temporal),
+            _ => Err(format!("unknown dither: {s}")),
+        }
+    }
+}
+
+impl Dither {
+    // Brightness offset for the cell at (x, y) of `frame` when quantizing to `levels`
+    // glyphs. It only depends on the cell position and frame, never on neighbouring
+    // results, so rows can be converted in any order or in parallel with
+    // bit-identical output
+    pub(crate) fn offset(self, x: u32, y: u32, frame: u32, levels: usize) -> f32 {
+        let threshold = match self {
+            Dither::None => return 0.0,
+            Dither::Ordered => bayer_threshold(x, y),
+            Dither::Temporal => temporal::threshold(x, y, frame),
+        };
+        (threshold - 0.5) / levels.max(1) as f32
+    }
+}
+
+// Threshold in 0..1 from the 8x8 Bayer matrix, built digit by digit from the
+// recursive [[0, 2], [3, 1]] pattern
+fn bayer_threshold(x: u32, y: u32) -> f32 {
+    const PATTERN: [[u32; 2]; 2] = [[0, 2], [3, 1]];
+    let mut value = 0;
+    for bit in 0..3 {
+        value = value * 4 + PATTERN[((y >> bit) & 1) as usize][((x >> bit) & 1) as usize];
+    }
+    (value as f32 + 0.5) / 64.0
+}
@@ -0,0 +1,44 @@
+use std::sync::OnceLock;
+
+use image::Rgb;
+
+use crate::color::{nearest, srgb_to_lab};
+
+// Every emoji is double width, so each cell spans two terminal columns
+pub(crate) const EMOJI_CELL_WIDTH: u32 = 2;
+
+// Common emoji and the average color of their usual rendering
+const EMOJI_COLORS: &[(&str, [u8; 3])] = &[
+    ("🟥", [221, 46, 68]),
+    ("🟧", [244, 144, 12]),
+    ("🟨", [253, 203, 88]),
+    ("🟩", [120, 177, 89]),
+    ("🟦", [85, 172, 238]),
+    ("🟪", [170, 142, 214]),
+    ("🟫", [193, 105, 79]),
+    ("⬛", [41, 47, 51]),
+    ("⬜", [230, 231, 232]),
+    ("🍓", [190, 25, 49]),
+    ("🍑", [255, 136, 108]),
+    ("🌸", [247, 189, 206]),
+    ("🐷", [244, 171, 186]),
+    ("🍆", [116, 76, 157]),
+    ("🌲", [62, 114, 27]),
+    ("🥑", [163, 192, 74]),
+    ("🌊", [59, 136, 195]),
+    ("🧊", [187, 221, 245]),
+    ("🍫", [104, 54, 30]),
+    ("🥚", [240, 232, 220]),
+    ("🌚", [102, 117, 127]),
+];
+
+pub(crate) fn nearest_emoji(color: Rgb<u8>) -> &'static str {
+    static LAB: OnceLock<Vec<[f32; 3]>> = OnceLock::new();
+    let palette = LAB.get_or_init(|| {
+        EMOJI_COLORS
+            .iter()
+            .map(|&(_, rgb)| srgb_to_lab(Rgb(rgb)))
+            .collect()
+    });
+    EMOJI_COLORS[nearest(color, palette)].0
+}
@@ -0,0 +1,69 @@
+use std::fmt::Write as _;
+
+use crate::ProgramError;
+
+// Inputs of a batch that couldn't be converted, reported together once the art is
+// written instead of interleaving errors with it
+#[derive(Debug, Default)]
+pub(crate) struct Failures {
+    failures: Vec<(String, ProgramError)>,
+}
+
+impl Failures {
+    pub(crate) fn record(&mut self, input: &str, err: ProgramError) {
+        self.failures.push((input.to_string(), err));
+    }
+
+    // Prints a table of the failed inputs and their error kinds on stderr (nothing
+    // when all of them converted) and lists them one per line in `failures_file`, so
+    // the batch can be retried with just those
+    pub(crate) fn report(
+        &self,
+        total: usize,
+        failures_file: Option<&str>,
+    ) -> Result<(), ProgramError> {
+        if let Some(path) = failures_file {
+            let list: String = self
+                .failures
+                .iter()
+                .map(|(input, _)| format!("{input}\n"))
+                .collect();
+            std::fs::write(path, list).map_err(|_| ProgramError::FailedToWriteToOutput)?;
+        }
+        if self.failures.is_empty() {
+            return Ok(());
+        }
+        let kind_width = self
+            .failures
+            .iter()
+            .map(|(_, err)| kind(err).len())
+            .max()
+            .unwrap_or(0)
+            .max("ERROR".len());
+        let mut table = format!("{} of {total} inputs failed:\n", self.failures.len());
+        let _ = writeln!(table, "  {:kind_width$}  INPUT", "ERROR");
+        for (input, err) in &self.failures {
+            let _ = writeln!(table, "  {:kind_width$}  {input}", kind(err));
+        }
+        eprint!("{table}");
+        Ok(())
+    }
+}
+
+fn kind(err: &ProgramError) -> &'static str {
+    match err {
+        ProgramError::InvalidInputPath => "not found",
+        ProgramError::FailedToDecodeInput
+        | ProgramError::DecoderCrashed
+        | ProgramError::DecoderTimedOut => "decode",
+        ProgramError::HostUnreachable => "unreachable",
+        ProgramError::DownloadRejected => "rejected",
+        ProgramError::FailedToDownload | ProgramError::DownloadInvalid => "download",
+        ProgramError::DownloadTimedOut => "timeout",
+        ProgramError::DownloadTooLarge | ProgramError::ImageTooLarge => "too large",
+        ProgramError::InvalidDataUri => "data uri",
+        ProgramError::NetworkDisabled => "no network",
+        ProgramError::EmptyInput => "empty",
+        _ => "error",
+    }
+}
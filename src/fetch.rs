@@ -0,0 +1,408 @@
+#[cfg(feature = "http")]
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io::{self, Read},
+    thread,
+    time::Duration,
+};
+
+#[cfg(feature = "http")]
+use reqwest::{
+    header::{
+        HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+        LAST_MODIFIED,
+    },
+    StatusCode,
+};
+
+#[cfg(feature = "http")]
+use crate::cache::{CacheEntry, HttpCache};
+
+use crate::ProgramError;
+
+#[derive(Debug, Clone)]
+pub struct Fetched {
+    pub bytes: Vec<u8>,
+    // None when the server sent no (readable) Content-Type
+    pub content_type: Option<String>,
+}
+
+// Downloads URL inputs; embedders can plug in their own HTTP stack
+pub trait Fetcher: std::fmt::Debug {
+    fn fetch(&self, url: &str) -> Result<Fetched, ProgramError>;
+
+    // Sends a JSON body, e.g. a chat webhook message
+    fn post_json(&self, url: &str, body: &str) -> Result<(), ProgramError> {
+        let _ = (url, body);
+        Err(ProgramError::WebhookFailed)
+    }
+}
+
+#[cfg(feature = "http")]
+const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+// HTTP_PROXY/HTTPS_PROXY/NO_PROXY are honored unless `proxy` overrides them
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+pub struct ReqwestFetcher {
+    // Covers the whole request including the body; None waits forever
+    pub timeout: Option<Duration>,
+    // In bytes, checked against Content-Length and enforced while reading the body
+    pub max_download_size: Option<u64>,
+    pub retry: RetryPolicy,
+    // Proxy URL (http://, https:// or socks5://) used for every request, still
+    // skipping the hosts listed in NO_PROXY
+    pub proxy: Option<String>,
+    // Sent with every download, e.g. ("Authorization", "Bearer ...")
+    pub headers: Vec<(String, String)>,
+    // User name and optional password
+    pub basic_auth: Option<(String, Option<String>)>,
+    // Many image hosts reject requests without one, so None sends "image2unicodeart/<version>"
+    pub user_agent: Option<String>,
+    pub cache: Option<HttpCache>,
+}
+
+#[cfg(feature = "http")]
+impl ReqwestFetcher {
+    pub const fn new() -> Self {
+        Self {
+            timeout: Some(Duration::from_secs(30)),
+            max_download_size: Some(64 * 1024 * 1024),
+            retry: RetryPolicy::none(),
+            proxy: None,
+            headers: Vec::new(),
+            basic_auth: None,
+            user_agent: None,
+            cache: None,
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+impl Default for ReqwestFetcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "http")]
+impl Fetcher for ReqwestFetcher {
+    fn fetch(&self, url: &str) -> Result<Fetched, ProgramError> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .timeout(self.timeout)
+            .user_agent(self.user_agent())
+            .default_headers(self.default_headers()?);
+        if let Some(proxy) = self.proxy()? {
+            builder = builder.proxy(proxy);
+        }
+        let client = builder
+            .build()
+            .map_err(|_| ProgramError::FailedToDownload)?;
+        let mut retry = 0;
+        loop {
+            match self.fetch_once(&client, url) {
+                Err((_, failure)) if retry < self.retry.retries && self.retry.applies(failure) => {
+                    thread::sleep(self.retry.delay(retry));
+                    retry += 1;
+                }
+                result => return result.map_err(|(err, _)| err),
+            }
+        }
+    }
+
+    // Download headers and credentials are meant for image hosts, so they aren't sent
+    fn post_json(&self, url: &str, body: &str) -> Result<(), ProgramError> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .timeout(self.timeout)
+            .user_agent(self.user_agent());
+        if let Some(proxy) = self.proxy()? {
+            builder = builder.proxy(proxy);
+        }
+        let client = builder.build().map_err(|_| ProgramError::WebhookFailed)?;
+        let mut retry = 0;
+        loop {
+            let failure = match client
+                .post(url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.to_owned())
+                .send()
+            {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                // Chat services answer bursts of messages with 429
+                Ok(response)
+                    if response.status().is_server_error()
+                        || response.status() == StatusCode::TOO_MANY_REQUESTS =>
+                {
+                    Failure::ServerError
+                }
+                Ok(_) => Failure::Permanent,
+                Err(err) => request_error(err).1,
+            };
+            if retry < self.retry.retries && self.retry.applies(failure) {
+                thread::sleep(self.retry.delay(retry));
+                retry += 1;
+            } else {
+                return Err(ProgramError::WebhookFailed);
+            }
+        }
+    }
+}
+
+// Request setup and response handling shared by the blocking and async clients
+#[cfg(feature = "http")]
+impl ReqwestFetcher {
+    pub(crate) fn user_agent(&self) -> &str {
+        self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT)
+    }
+
+    pub(crate) fn default_headers(&self) -> Result<HeaderMap, ProgramError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| ProgramError::InvalidHeader)?;
+            let value =
+                HeaderValue::from_str(value.trim()).map_err(|_| ProgramError::InvalidHeader)?;
+            headers.append(name, value);
+        }
+        Ok(headers)
+    }
+
+    pub(crate) fn proxy(&self) -> Result<Option<reqwest::Proxy>, ProgramError> {
+        self.proxy
+            .as_deref()
+            .map(|proxy| {
+                reqwest::Proxy::all(proxy)
+                    .map(|proxy| proxy.no_proxy(reqwest::NoProxy::from_env()))
+                    .map_err(|_| ProgramError::InvalidProxy)
+            })
+            .transpose()
+    }
+
+    // The cached copy of `url`, if any, and the headers revalidating it instead of
+    // downloading it again
+    pub(crate) fn revalidation(&self, url: &str) -> (Option<CacheEntry>, HeaderMap) {
+        let cached = self.cache.as_ref().and_then(|cache| cache.lookup(url));
+        let mut headers = HeaderMap::new();
+        if let Some(entry) = &cached {
+            let validators = [
+                (IF_NONE_MATCH, &entry.etag),
+                (IF_MODIFIED_SINCE, &entry.last_modified),
+            ];
+            for (name, value) in validators {
+                if let Some(value) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+                    headers.insert(name, value);
+                }
+            }
+        }
+        (cached, headers)
+    }
+
+    // Rejects server errors and oversized bodies before reading them, and answers
+    // a 304 from the cache. Ok(None) means the body should be read
+    pub(crate) fn check_response(
+        &self,
+        url: &str,
+        status: StatusCode,
+        content_length: Option<u64>,
+        cached: Option<CacheEntry>,
+    ) -> Result<Option<Fetched>, (ProgramError, Failure)> {
+        if status.is_server_error() {
+            return Err((ProgramError::FailedToDownload, Failure::ServerError));
+        }
+        if status.is_client_error() {
+            return Err((ProgramError::DownloadRejected, Failure::Permanent));
+        }
+        if status == StatusCode::NOT_MODIFIED {
+            return self
+                .cache
+                .as_ref()
+                .zip(cached)
+                .and_then(|(cache, entry)| cache.load(url, entry))
+                .map(Some)
+                .ok_or((ProgramError::FailedToDownload, Failure::Permanent));
+        }
+        if content_length.is_some_and(|length| length > self.max_download_size()) {
+            return Err((ProgramError::DownloadTooLarge, Failure::Permanent));
+        }
+        Ok(None)
+    }
+
+    pub(crate) fn max_download_size(&self) -> u64 {
+        self.max_download_size.unwrap_or(u64::MAX)
+    }
+
+    pub(crate) fn finish_response(
+        &self,
+        url: &str,
+        status: StatusCode,
+        headers: &HeaderMap,
+        bytes: Vec<u8>,
+    ) -> Result<Fetched, (ProgramError, Failure)> {
+        if bytes.len() as u64 > self.max_download_size() {
+            return Err((ProgramError::DownloadTooLarge, Failure::Permanent));
+        }
+        let content_type = header(headers, CONTENT_TYPE);
+        if let Some(cache) = self.cache.as_ref().filter(|_| status.is_success()) {
+            cache.store(
+                &CacheEntry {
+                    url: url.to_string(),
+                    etag: header(headers, ETAG),
+                    last_modified: header(headers, LAST_MODIFIED),
+                    content_type: content_type.clone(),
+                },
+                &bytes,
+            );
+        }
+        Ok(Fetched {
+            bytes,
+            content_type,
+        })
+    }
+
+    fn fetch_once(
+        &self,
+        client: &reqwest::blocking::Client,
+        url: &str,
+    ) -> Result<Fetched, (ProgramError, Failure)> {
+        let mut request = client.get(url);
+        if let Some((user, password)) = &self.basic_auth {
+            request = request.basic_auth(user, password.as_ref());
+        }
+        let (cached, validators) = self.revalidation(url);
+        let response = request.headers(validators).send().map_err(request_error)?;
+        let status = response.status();
+        if let Some(fetched) =
+            self.check_response(url, status, response.content_length(), cached)?
+        {
+            return Ok(fetched);
+        }
+
+        // Content-Length can lie (or be missing), so read at most one byte past the cap
+        let headers = response.headers().clone();
+        let mut bytes = Vec::new();
+        response
+            .take(self.max_download_size().saturating_add(1))
+            .read_to_end(&mut bytes)
+            .map_err(body_error)?;
+        self.finish_response(url, status, &headers, bytes)
+    }
+}
+
+// Retries of transient failures, with exponential backoff and jitter so that many
+// clients failing at once don't retry in lockstep
+#[cfg(feature = "http")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    // Extra attempts after the first one
+    pub retries: u32,
+    // Delay before the first retry, doubled for every further one
+    pub backoff: Duration,
+    pub on_server_error: bool,
+    pub on_timeout: bool,
+}
+
+#[cfg(feature = "http")]
+impl RetryPolicy {
+    pub const fn none() -> Self {
+        Self {
+            retries: 0,
+            backoff: Duration::from_millis(500),
+            on_server_error: true,
+            on_timeout: true,
+        }
+    }
+
+    // Connection failures are always worth another attempt
+    pub(crate) fn applies(&self, failure: Failure) -> bool {
+        match failure {
+            Failure::Connect => true,
+            Failure::ServerError => self.on_server_error,
+            Failure::Timeout => self.on_timeout,
+            Failure::Permanent => false,
+        }
+    }
+
+    // Half of the backoff is fixed and half random
+    pub(crate) fn delay(&self, retry: u32) -> Duration {
+        let delay = self.backoff.saturating_mul(1 << retry.min(16));
+        delay / 2 + delay.mul_f64(random_fraction() / 2.0)
+    }
+}
+
+#[cfg(feature = "http")]
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+#[cfg(feature = "http")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Failure {
+    Connect,
+    ServerError,
+    Timeout,
+    Permanent,
+}
+
+// Every RandomState is seeded differently, which is all the randomness jitter needs
+#[cfg(feature = "http")]
+fn random_fraction() -> f64 {
+    let hash = RandomState::new().build_hasher().finish();
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(feature = "http")]
+fn header(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+}
+
+#[cfg(feature = "http")]
+pub(crate) fn request_error(err: reqwest::Error) -> (ProgramError, Failure) {
+    if err.is_timeout() {
+        (ProgramError::DownloadTimedOut, Failure::Timeout)
+    } else if err.is_connect() {
+        (ProgramError::HostUnreachable, Failure::Connect)
+    } else {
+        (ProgramError::FailedToDownload, Failure::Permanent)
+    }
+}
+
+#[cfg(feature = "http")]
+fn body_error(err: io::Error) -> (ProgramError, Failure) {
+    let timed_out = err.kind() == io::ErrorKind::TimedOut
+        || err
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<reqwest::Error>())
+            .is_some_and(reqwest::Error::is_timeout);
+    if timed_out {
+        (ProgramError::DownloadTimedOut, Failure::Timeout)
+    } else {
+        (ProgramError::DownloadInvalid, Failure::Permanent)
+    }
+}
+
+// Rejects every URL, for builds and embedders that must never touch the network
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoNetwork;
+
+impl Fetcher for NoNetwork {
+    fn fetch(&self, _url: &str) -> Result<Fetched, ProgramError> {
+        Err(ProgramError::NetworkDisabled)
+    }
+}
+
+#[cfg(feature = "http")]
+pub fn default_fetcher() -> &'static dyn Fetcher {
+    static DEFAULT: ReqwestFetcher = ReqwestFetcher::new();
+    &DEFAULT
+}
+
+#[cfg(not(feature = "http"))]
+pub fn default_fetcher() -> &'static dyn Fetcher {
+    &NoNetwork
+}
@@ -0,0 +1,118 @@
+// C ABI for embedding the converter, see include/i2ua.h. Every pointer argument
+// must be NULL or valid for the call (strings NUL-terminated UTF-8, `bytes` readable
+// for `len` bytes), and returned strings must be released with i2ua_free_string
+#![allow(clippy::missing_safety_doc)]
+
+use std::{
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use image::ImageFormat;
+
+use crate::{convert_bytes, ColorMode, ConversionOptions, OutputFormat};
+
+// Zero or NULL fields keep their defaults
+#[repr(C)]
+pub struct I2uaOptions {
+    pub width: u32,
+    pub charset: *const c_char,
+    // "text", "html", "svg" or "markdown"
+    pub format: *const c_char,
+    // "none", "16", "256" or "truecolor"
+    pub color: *const c_char,
+}
+
+// Converts an encoded image, returning the art or NULL on failure
+#[no_mangle]
+pub unsafe extern "C" fn i2ua_convert_bytes(
+    bytes: *const u8,
+    len: usize,
+    options: *const I2uaOptions,
+) -> *mut c_char {
+    if bytes.is_null() {
+        return ptr::null_mut();
+    }
+    let bytes = std::slice::from_raw_parts(bytes, len);
+    convert(bytes, None, options.as_ref())
+}
+
+// Reads and converts an image file, returning the art or NULL on failure
+#[no_mangle]
+pub unsafe extern "C" fn i2ua_convert_file(
+    path: *const c_char,
+    options: *const I2uaOptions,
+) -> *mut c_char {
+    let Some(path) = c_str(path) else {
+        return ptr::null_mut();
+    };
+    let Ok(bytes) = std::fs::read(path) else {
+        return ptr::null_mut();
+    };
+    convert(&bytes, ImageFormat::from_path(path).ok(), options.as_ref())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn i2ua_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+unsafe fn convert(
+    bytes: &[u8],
+    format: Option<ImageFormat>,
+    options: Option<&I2uaOptions>,
+) -> *mut c_char {
+    let defaults = ConversionOptions::default();
+    let Some(options) = (match options {
+        Some(options) => conversion_options(options, defaults),
+        None => Some(defaults),
+    }) else {
+        return ptr::null_mut();
+    };
+    // Binary formats can't be returned as C strings
+    if options.format.is_binary() {
+        return ptr::null_mut();
+    }
+    // A panic must not unwind into the caller's frames
+    panic::catch_unwind(AssertUnwindSafe(|| convert_bytes(bytes, format, &options)))
+        .ok()
+        .and_then(Result::ok)
+        .and_then(|output| CString::new(output.art).ok())
+        .map_or(ptr::null_mut(), CString::into_raw)
+}
+
+unsafe fn conversion_options<'a>(
+    options: &'a I2uaOptions,
+    defaults: ConversionOptions<'a>,
+) -> Option<ConversionOptions<'a>> {
+    let format: Option<OutputFormat> = parse(options.format)?;
+    let color: Option<ColorMode> = parse(options.color)?;
+    Some(ConversionOptions {
+        output_width: (options.width > 0).then_some(options.width),
+        charset: match options.charset.is_null() {
+            true => defaults.charset,
+            false => c_str(options.charset)?,
+        },
+        format: format.unwrap_or(defaults.format),
+        color: color.unwrap_or(defaults.color),
+        ..defaults
+    })
+}
+
+// None for invalid values, Some(None) for NULL
+unsafe fn parse<T: std::str::FromStr>(value: *const c_char) -> Option<Option<T>> {
+    if value.is_null() {
+        return Some(None);
+    }
+    c_str(value)?.parse().ok().map(Some)
+}
+
+unsafe fn c_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok()
+}
@@ -0,0 +1,164 @@
+use std::str::FromStr;
+
+use image::{Rgba, RgbaImage};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generator {
+    Plasma,
+    Life,
+    Fire,
+}
+
+impl FromStr for Generator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plasma" => Ok(Self::Plasma),
+            "life" => Ok(Self::Life),
+            "fire" => Ok(Self::Fire),
+            _ => Err(format!("unknown generator: {s}")),
+        }
+    }
+}
+
+pub(crate) fn generate_frames(generator: Generator, frame_count: usize) -> Vec<RgbaImage> {
+    match generator {
+        Generator::Plasma => (0..frame_count).map(|i| plasma(i as f32 * 0.1)).collect(),
+        Generator::Life => {
+            let mut life = Life::new();
+            (0..frame_count)
+                .map(|_| {
+                    let frame = life.draw();
+                    life.step();
+                    frame
+                })
+                .collect()
+        }
+        Generator::Fire => {
+            let mut fire = Fire::new();
+            (0..frame_count)
+                .map(|_| {
+                    fire.step();
+                    fire.draw()
+                })
+                .collect()
+        }
+    }
+}
+
+fn plasma(t: f32) -> RgbaImage {
+    RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| {
+        let (x, y) = (x as f32 / 16.0, y as f32 / 16.0);
+        let v = (x + t).sin()
+            + ((y + t) / 2.0).sin()
+            + ((x + y + t) / 2.0).sin()
+            + ((x * x + y * y).sqrt() + t).sin();
+        let channel =
+            |phase: f32| ((v * std::f32::consts::PI / 2.0 + phase).sin() * 127.5 + 127.5) as u8;
+        Rgba([channel(0.0), channel(2.0), channel(4.0), u8::MAX])
+    })
+}
+
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+struct Life {
+    cells: Vec<bool>,
+}
+
+impl Life {
+    fn new() -> Self {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let cells = (0..WIDTH * HEIGHT).map(|_| rng.next() % 3 == 0).collect();
+        Self { cells }
+    }
+
+    fn step(&mut self) {
+        let (w, h) = (WIDTH as i32, HEIGHT as i32);
+        let alive = |x: i32, y: i32| self.cells[(y.rem_euclid(h) * w + x.rem_euclid(w)) as usize];
+        let next = (0..h)
+            .flat_map(|y| (0..w).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let neighbours = [
+                    (-1, -1),
+                    (0, -1),
+                    (1, -1),
+                    (-1, 0),
+                    (1, 0),
+                    (-1, 1),
+                    (0, 1),
+                    (1, 1),
+                ]
+                .iter()
+                .filter(|(dx, dy)| alive(x + dx, y + dy))
+                .count();
+                matches!((alive(x, y), neighbours), (true, 2) | (_, 3))
+            })
+            .collect();
+        self.cells = next;
+    }
+
+    fn draw(&self) -> RgbaImage {
+        RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| {
+            let v = if self.cells[(y * WIDTH + x) as usize] {
+                u8::MAX
+            } else {
+                0
+            };
+            Rgba([v, v, v, u8::MAX])
+        })
+    }
+}
+
+struct Fire {
+    heat: Vec<u8>,
+    rng: Rng,
+}
+
+impl Fire {
+    fn new() -> Self {
+        let mut heat = vec![0; (WIDTH * HEIGHT) as usize];
+        let bottom = ((HEIGHT - 1) * WIDTH) as usize;
+        heat[bottom..].fill(u8::MAX);
+        Self {
+            heat,
+            rng: Rng(0x2545_f491_4f6c_dd1d),
+        }
+    }
+
+    // Each pixel takes the heat of the one below it, slightly cooled and drifted sideways
+    fn step(&mut self) {
+        for y in 0..HEIGHT - 1 {
+            for x in 0..WIDTH {
+                let r = self.rng.next();
+                let below = self.heat[((y + 1) * WIDTH + x) as usize];
+                let cooled = below.saturating_sub((r % 12) as u8);
+                let drift = (x as i64 + (r >> 8) as i64 % 3 - 1).clamp(0, WIDTH as i64 - 1) as u32;
+                self.heat[(y * WIDTH + drift) as usize] = cooled;
+            }
+        }
+    }
+
+    fn draw(&self) -> RgbaImage {
+        RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| {
+            let h = self.heat[(y * WIDTH + x) as usize] as u32;
+            // black -> red -> yellow -> white
+            let r = (h * 3).min(255) as u8;
+            let g = (h * 3).saturating_sub(255).min(255) as u8;
+            let b = (h * 3).saturating_sub(510).min(255) as u8;
+            Rgba([r, g, b, u8::MAX])
+        })
+    }
+}
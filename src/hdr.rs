@@ -0,0 +1,105 @@
+use std::str::FromStr;
+
+use image::{DynamicImage, Rgba32FImage};
+
+use crate::Luma;
+
+// Luminance the sigmoid curve is centered on, photographic mid-gray
+const MID_GRAY: f32 = 0.18;
+
+// Steepness of the sigmoid over log luminance
+const SIGMOID_SLOPE: f32 = 1.5;
+
+// Source rows measured at a time, so measuring doesn't convert the image to floats
+// all at once
+const BAND_ROWS: u32 = 64;
+
+// Curve compressing high-dynamic-range luminance into 0..1, so bright and dark areas
+// keep their detail instead of landing on the charset's extreme glyphs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapping {
+    // Extended Reinhard, rolling off the highlights towards the white point
+    Reinhard,
+    // S-curve over log luminance around mid-gray, also lifting the shadows
+    Sigmoid,
+}
+
+impl FromStr for ToneMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reinhard" => Ok(Self::Reinhard),
+            "sigmoid" => Ok(Self::Sigmoid),
+            _ => Err(format!("unknown tone mapping: {s}")),
+        }
+    }
+}
+
+impl ToneMapping {
+    // Maps `luminance` to 0..1, `white` (and above) to 1
+    fn map(self, luminance: f32, white: f32) -> f32 {
+        let mapped = match self {
+            ToneMapping::Reinhard => {
+                luminance * (1.0 + luminance / (white * white)) / (1.0 + luminance)
+            }
+            ToneMapping::Sigmoid => sigmoid(luminance) / sigmoid(white),
+        };
+        mapped.clamp(0.0, 1.0)
+    }
+}
+
+fn sigmoid(luminance: f32) -> f32 {
+    let l = luminance.powf(SIGMOID_SLOPE);
+    l / (l + MID_GRAY.powf(SIGMOID_SLOPE))
+}
+
+// The brightest exposed luminance of the image, or exposed SDR white when brighter
+pub(crate) fn white_point(img: &DynamicImage, exposure: f32, luma: Luma) -> f32 {
+    let gain = exposure.exp2();
+    let (w, h) = (img.width(), img.height());
+    (0..h)
+        .step_by(BAND_ROWS as usize)
+        .map(|y| {
+            let band = img.crop_imm(0, y, w, BAND_ROWS.min(h - y)).to_rgba32f();
+            band.pixels()
+                .map(|pixel| exposed_luminance(pixel.0, gain, luma))
+                .fold(gain, f32::max)
+        })
+        .fold(gain, f32::max)
+}
+
+// Scales the image by `exposure` stops and tone maps its luminance on a float buffer,
+// then rescales every pixel's color to the mapped luminance, keeping hue and alpha.
+// `white` comes from `white_point`, measured on the whole image or sequence so its
+// strips and frames all map alike
+pub(crate) fn tone_map(
+    img: &DynamicImage,
+    mapping: ToneMapping,
+    exposure: f32,
+    luma: Luma,
+    white: f32,
+) -> Rgba32FImage {
+    let gain = exposure.exp2();
+    let mut rgba = img.to_rgba32f();
+    let luminance: Vec<f32> = rgba
+        .pixels()
+        .map(|pixel| exposed_luminance(pixel.0, gain, luma))
+        .collect();
+    for (pixel, &l) in rgba.pixels_mut().zip(&luminance) {
+        let scale = if l > 0.0 {
+            mapping.map(l, white) / l * gain
+        } else {
+            0.0
+        };
+        for c in &mut pixel.0[..3] {
+            *c = (*c * scale).clamp(0.0, 1.0);
+        }
+    }
+    rgba
+}
+
+fn exposed_luminance([r, g, b, _]: [f32; 4], gain: f32, luma: Luma) -> f32 {
+    let (wr, wg, wb) = luma.weights();
+    (wr * r + wg * g + wb * b).max(0.0) * gain
+}
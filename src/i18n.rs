@@ -0,0 +1,76 @@
+use clap::Command;
+use fluent_bundle::{FluentArgs, FluentBundle, FluentResource};
+use unic_langid::LanguageIdentifier;
+
+const DEFAULT_MESSAGES: &str = include_str!("../locales/en-US.ftl");
+const MESSAGES_ENV: &str = "IMAGE2UNICODEART_MESSAGES";
+
+pub struct Messages {
+    bundle: FluentBundle<FluentResource>,
+}
+
+impl Messages {
+    // English messages, overridden by the catalog in $IMAGE2UNICODEART_MESSAGES if set
+    pub fn load() -> Self {
+        let mut bundle = FluentBundle::new(vec![locale()]);
+        // Unicode isolation marks show up as garbage in many terminals
+        bundle.set_use_isolating(false);
+
+        let default = FluentResource::try_new(DEFAULT_MESSAGES.to_string())
+            .expect("built-in message catalog is valid");
+        let _ = bundle.add_resource(default);
+
+        if let Some(catalog) =
+            std::env::var_os(MESSAGES_ENV).and_then(|path| std::fs::read_to_string(path).ok())
+        {
+            // Keep whatever parsed successfully even if some entries are broken
+            let resource = FluentResource::try_new(catalog).unwrap_or_else(|(res, _)| res);
+            bundle.add_resource_overriding(resource);
+        }
+
+        Self { bundle }
+    }
+
+    pub fn get(&self, id: &str) -> Option<String> {
+        self.format(id, &[])
+    }
+
+    pub fn format(&self, id: &str, args: &[(&str, &str)]) -> Option<String> {
+        let pattern = self.bundle.get_message(id)?.value()?;
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in args {
+            fluent_args.set(*name, *value);
+        }
+        let mut errors = Vec::new();
+        let text = self
+            .bundle
+            .format_pattern(pattern, Some(&fluent_args), &mut errors);
+        Some(text.into_owned())
+    }
+
+    pub fn localize_command(&self, mut command: Command) -> Command {
+        if let Some(about) = self.get("about") {
+            command = command.about(about);
+        }
+        let ids: Vec<String> = command
+            .get_arguments()
+            .map(|arg| arg.get_id().to_string())
+            .collect();
+        for id in ids {
+            if let Some(help) = self.get(&format!("help-{id}")) {
+                command = command.mut_arg(id, |arg| arg.help(help));
+            }
+        }
+        command
+    }
+}
+
+fn locale() -> LanguageIdentifier {
+    std::env::var("LANG")
+        .ok()
+        .and_then(|lang| {
+            let tag = lang.split('.').next().unwrap_or_default().replace('_', "-");
+            tag.parse().ok()
+        })
+        .unwrap_or_else(|| "en-US".parse().unwrap())
+}
@@ -0,0 +1,238 @@
+use std::{
+    io::{self, Read, Write},
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use image::{DynamicImage, ImageFormat, Rgba32FImage, RgbaImage};
+
+#[cfg(feature = "svg")]
+use crate::ConversionOptions;
+use crate::{decode_bytes, DecodeLimits, ProgramError};
+
+// Applications embedding the library must forward this argument and the ones after it
+// to `run_decode_worker` early in `main`, since the worker re-executes the current binary
+pub const DECODE_WORKER_ARG: &str = "--decode-worker";
+
+// What a worker does with its input, its first argument
+const JOB_IMAGE: &str = "image";
+#[cfg(feature = "raw")]
+const JOB_RAW: &str = "raw";
+#[cfg(feature = "svg")]
+const JOB_SVG_SIZE: &str = "svg-size";
+#[cfg(feature = "svg")]
+const JOB_SVG: &str = "svg";
+
+const EXIT_DECODE_FAILED: i32 = 2;
+const EXIT_IO_FAILED: i32 = 3;
+const EXIT_TOO_LARGE: i32 = 4;
+const EXIT_EMPTY: i32 = 5;
+
+// Wall-clock time a worker gets before it's killed, so inputs crafted to make the
+// decoder spin can't hang the conversion
+const DECODE_TIMEOUT: Duration = Duration::from_secs(30);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// Pixel layouts of the worker's output; deeper than 8 bits is sent as floats so
+// 16-bit and HDR inputs keep their precision
+const DEPTH_U8: u8 = 8;
+const DEPTH_F32: u8 = 32;
+
+pub fn decode_isolated(
+    bytes: &[u8],
+    format: Option<ImageFormat>,
+    limits: DecodeLimits,
+) -> Result<DynamicImage, ProgramError> {
+    let extension = format.and_then(|format| format.extensions_str().first().copied());
+    read_image(&run_worker(JOB_IMAGE, extension, bytes, limits)?)
+}
+
+#[cfg(feature = "raw")]
+pub(crate) fn develop_isolated(
+    bytes: &[u8],
+    limits: DecodeLimits,
+) -> Result<DynamicImage, ProgramError> {
+    read_image(&run_worker(JOB_RAW, None, bytes, limits)?)
+}
+
+// The render size depends on the conversion options, so one worker reads the size the
+// SVG declares and a second one renders it at the scale worked out from that
+#[cfg(feature = "svg")]
+pub(crate) fn rasterize_isolated(
+    bytes: &[u8],
+    options: &ConversionOptions,
+    limits: DecodeLimits,
+) -> Result<DynamicImage, ProgramError> {
+    let output = run_worker(JOB_SVG_SIZE, None, bytes, limits)?;
+    let size = match output[..] {
+        [w0, w1, w2, w3, h0, h1, h2, h3] => (
+            f32::from_le_bytes([w0, w1, w2, w3]),
+            f32::from_le_bytes([h0, h1, h2, h3]),
+        ),
+        _ => return Err(ProgramError::DecoderCrashed),
+    };
+    let scale = crate::svg::scale_for(size, options).to_string();
+    read_image(&run_worker(JOB_SVG, Some(&scale), bytes, limits)?)
+}
+
+fn run_worker(
+    job: &str,
+    parameter: Option<&str>,
+    bytes: &[u8],
+    limits: DecodeLimits,
+) -> Result<Vec<u8>, ProgramError> {
+    let exe = std::env::current_exe().map_err(|_| ProgramError::DecoderCrashed)?;
+    let mut command = Command::new(exe);
+    // 0 means unlimited, as on the command line
+    command
+        .arg(DECODE_WORKER_ARG)
+        .arg(job)
+        .arg(limits.max_pixels.unwrap_or(0).to_string())
+        .arg(limits.max_memory.unwrap_or(0).to_string())
+        .args(parameter);
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|_| ProgramError::DecoderCrashed)?;
+
+    let mut stdin = child.stdin.take().ok_or(ProgramError::DecoderCrashed)?;
+    let mut stdout = child.stdout.take().ok_or(ProgramError::DecoderCrashed)?;
+    let (status, output) = thread::scope(|scope| {
+        // Feed the input and drain the output from separate threads so a large decoded
+        // image filling the stdout pipe can't deadlock against our pending writes
+        scope.spawn(move || stdin.write_all(bytes));
+        let reader = scope.spawn(move || {
+            let mut output = Vec::new();
+            stdout.read_to_end(&mut output).map(|_| output)
+        });
+        let status = wait_with_timeout(&mut child);
+        (status, reader.join())
+    });
+    let status = status?;
+    let output = output
+        .ok()
+        .and_then(Result::ok)
+        .ok_or(ProgramError::DecoderCrashed)?;
+
+    match status.code() {
+        Some(0) => Ok(output),
+        Some(EXIT_DECODE_FAILED) => Err(ProgramError::FailedToDecodeInput),
+        Some(EXIT_TOO_LARGE) => Err(ProgramError::ImageTooLarge),
+        Some(EXIT_EMPTY) => Err(ProgramError::EmptyInput),
+        _ => Err(ProgramError::DecoderCrashed),
+    }
+}
+
+// Kills the worker once it overruns the timeout or can't be waited on, which also
+// closes its pipes and lets the feeding threads finish
+fn wait_with_timeout(child: &mut Child) -> Result<ExitStatus, ProgramError> {
+    let deadline = Instant::now() + DECODE_TIMEOUT;
+    let result = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Ok(status),
+            Ok(None) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+            Ok(None) => break Err(ProgramError::DecoderTimedOut),
+            Err(_) => break Err(ProgramError::DecoderCrashed),
+        }
+    };
+    let _ = child.kill();
+    let _ = child.wait();
+    result
+}
+
+fn read_image(output: &[u8]) -> Result<DynamicImage, ProgramError> {
+    let (header, data) = output
+        .split_at_checked(9)
+        .ok_or(ProgramError::DecoderCrashed)?;
+    let width = u32::from_le_bytes(header[0..4].try_into().unwrap());
+    let height = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    match header[8] {
+        DEPTH_U8 => RgbaImage::from_raw(width, height, data.to_vec())
+            .map(DynamicImage::ImageRgba8)
+            .ok_or(ProgramError::DecoderCrashed),
+        DEPTH_F32 => {
+            let samples = data
+                .chunks_exact(4)
+                .map(|sample| f32::from_le_bytes(sample.try_into().unwrap()))
+                .collect();
+            Rgba32FImage::from_raw(width, height, samples)
+                .map(DynamicImage::ImageRgba32F)
+                .ok_or(ProgramError::DecoderCrashed)
+        }
+        _ => Err(ProgramError::DecoderCrashed),
+    }
+}
+
+fn write_image(img: &DynamicImage) -> Vec<u8> {
+    let color = img.color();
+    let deep = color.bytes_per_pixel() / color.channel_count() > 1;
+    let mut output = [img.width().to_le_bytes(), img.height().to_le_bytes()].concat();
+    if deep {
+        output.push(DEPTH_F32);
+        let samples = img.to_rgba32f().into_raw();
+        output.extend(samples.iter().flat_map(|sample| sample.to_le_bytes()));
+    } else {
+        output.push(DEPTH_U8);
+        output.extend_from_slice(&img.to_rgba8().into_raw());
+    }
+    output
+}
+
+// Arguments: the job, max pixels and max decoder memory (0 for unlimited), then the
+// job's parameter: the image format's extension, or the scale SVGs are rendered at.
+// The limits are checked here, the host parses nothing of the input
+pub fn run_decode_worker(mut args: impl Iterator<Item = String>) -> i32 {
+    let job = args.next().unwrap_or_default();
+    let mut limit = || {
+        args.next()
+            .and_then(|arg| arg.parse().ok())
+            .filter(|&max: &u64| max > 0)
+    };
+    let limits = DecodeLimits {
+        max_pixels: limit(),
+        max_memory: limit(),
+    };
+    let parameter = args.next();
+
+    let mut bytes = Vec::new();
+    if io::stdin().read_to_end(&mut bytes).is_err() {
+        return EXIT_IO_FAILED;
+    }
+    let output = match job.as_str() {
+        JOB_IMAGE => {
+            let format = parameter.and_then(ImageFormat::from_extension);
+            decode_bytes(&bytes, format, false, limits).map(|img| write_image(&img))
+        }
+        #[cfg(feature = "raw")]
+        JOB_RAW => crate::raw::develop(&bytes, limits).map(|img| write_image(&img)),
+        #[cfg(feature = "svg")]
+        JOB_SVG_SIZE => crate::svg::declared_size(&bytes)
+            .map(|(w, h)| [w.to_le_bytes(), h.to_le_bytes()].concat()),
+        #[cfg(feature = "svg")]
+        JOB_SVG => parameter
+            .and_then(|scale| scale.parse().ok())
+            .ok_or(ProgramError::FailedToDecodeInput)
+            .and_then(|scale| crate::svg::render(&bytes, scale, limits))
+            .map(|img| write_image(&img)),
+        _ => Err(ProgramError::FailedToDecodeInput),
+    };
+    let output = match output {
+        Ok(output) => output,
+        Err(ProgramError::ImageTooLarge) => return EXIT_TOO_LARGE,
+        Err(ProgramError::EmptyInput) => return EXIT_EMPTY,
+        Err(_) => return EXIT_DECODE_FAILED,
+    };
+    let mut stdout = io::stdout().lock();
+    if stdout
+        .write_all(&output)
+        .and_then(|_| stdout.flush())
+        .is_err()
+    {
+        return EXIT_IO_FAILED;
+    }
+    0
+}
@@ -0,0 +1,73 @@
+use image::Rgb;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    parse_color,
+    render::{hex_color, Renderer},
+    AsciiImage, ProgramError,
+};
+
+// The art split into aligned per-cell layers, so colors can be edited independently
+// of the glyphs and re-rendered in any format with --from-layers. The renderers
+// draw every cell over one background color, so that's a single value
+#[derive(Serialize, Deserialize)]
+struct Layers {
+    width: u32,
+    height: u32,
+    background: String,
+    glyphs: Vec<Vec<String>>,
+    foreground: Vec<Vec<String>>,
+    transparent: Vec<Vec<bool>>,
+}
+
+pub(crate) struct LayersRenderer {
+    pub(crate) background: Rgb<u8>,
+}
+
+impl Renderer for LayersRenderer {
+    fn render(&self, art: &AsciiImage) -> Vec<u8> {
+        let layers = Layers {
+            width: art.dimensions.0,
+            height: art.dimensions.1,
+            background: hex_color(self.background),
+            glyphs: art.data.clone(),
+            foreground: art
+                .colors
+                .iter()
+                .map(|row| row.iter().copied().map(hex_color).collect())
+                .collect(),
+            transparent: art.transparent.clone(),
+        };
+        let mut json = serde_json::to_vec(&layers).unwrap_or_default();
+        json.push(b'\n');
+        json
+    }
+}
+
+// The art and background color of a (possibly edited) layers bundle
+pub(crate) fn parse_layers(bytes: &[u8]) -> Result<(AsciiImage, Rgb<u8>), ProgramError> {
+    let layers: Layers = serde_json::from_slice(bytes).map_err(|_| ProgramError::InvalidLayers)?;
+    let (w, h) = (layers.width as usize, layers.height as usize);
+    let aligned = |rows: &[Vec<_>]| rows.len() == h && rows.iter().all(|row| row.len() == w);
+    if !aligned(&layers.glyphs) || !aligned(&layers.foreground) || !aligned(&layers.transparent) {
+        return Err(ProgramError::InvalidLayers);
+    }
+    let colors = layers
+        .foreground
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|color| parse_color(color))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ProgramError::InvalidLayers)?;
+    let background = parse_color(&layers.background).map_err(|_| ProgramError::InvalidLayers)?;
+    let art = AsciiImage {
+        dimensions: (layers.width, layers.height),
+        data: layers.glyphs,
+        transparent: layers.transparent,
+        colors,
+    };
+    Ok((art, background))
+}
@@ -0,0 +1,81 @@
+use std::str::FromStr;
+
+use unicode_width::UnicodeWidthChar;
+
+use crate::{terminal_height, terminal_width, AsciiImage, ConversionOptions};
+
+// The output box is --width (or the terminal's) columns by --height (or the
+// terminal's, less a row for the prompt) rows, and the art is fitted inside it
+pub(crate) fn box_options<'a>(options: &ConversionOptions<'a>) -> ConversionOptions<'a> {
+    ConversionOptions {
+        output_width: Some(options.output_width.unwrap_or_else(terminal_width)),
+        max_rows: Some(
+            options
+                .max_rows
+                .unwrap_or_else(|| terminal_height().saturating_sub(1).max(1)),
+        ),
+        ..options.clone()
+    }
+}
+
+// Centers the art in the output box, filling the cells around it with `fill`, so
+// the output always has the box's exact size
+pub(crate) fn letterbox(art: &AsciiImage, options: &ConversionOptions, fill: char) -> AsciiImage {
+    let cell_width = options.cell_width();
+    let columns = options.output_width.unwrap_or_else(terminal_width) / cell_width;
+    let rows = options.max_rows.unwrap_or(art.dimensions.1);
+    let dimensions = (columns.max(art.dimensions.0), rows.max(art.dimensions.1));
+
+    let fill_width = fill.width().unwrap_or(1).max(1) as u32;
+    let glyph = fill
+        .to_string()
+        .repeat((cell_width / fill_width).max(1) as usize);
+    let mut boxed = AsciiImage::create_filled(dimensions, &glyph);
+    let offset = (
+        (dimensions.0 - art.dimensions.0) / 2,
+        (dimensions.1 - art.dimensions.1) / 2,
+    );
+    boxed.blit(art, offset);
+    boxed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl FromStr for Align {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(Self::Left),
+            "center" => Ok(Self::Center),
+            "right" => Ok(Self::Right),
+            _ => Err(format!("unknown alignment: {s}")),
+        }
+    }
+}
+
+// Shifts the art right within the terminal's width by prepending blank cells to
+// every row. They're transparent, so overlays leave what's under them alone
+pub(crate) fn align(art: AsciiImage, align: Align, cell_width: u32) -> AsciiImage {
+    let slack = (terminal_width() / cell_width).saturating_sub(art.dimensions.0);
+    let offset = match align {
+        Align::Left => 0,
+        Align::Center => slack / 2,
+        Align::Right => slack,
+    };
+    if offset == 0 {
+        return art;
+    }
+    let (w, h) = art.dimensions;
+    let mut aligned = AsciiImage::create_blank((w + offset, h), cell_width);
+    for row in &mut aligned.transparent {
+        row.fill(true);
+    }
+    aligned.blit(&art, (offset, 0));
+    aligned
+}
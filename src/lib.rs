@@ -1,45 +1,706 @@
+mod animation;
+mod ans;
+mod autotune;
+mod border;
+#[cfg(feature = "http")]
+mod cache;
+mod calibration;
+mod capabilities;
+mod caption;
+mod charset;
+mod color;
+mod crop;
+mod dither;
+mod emoji;
+mod failures;
+mod fetch;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod generators;
+mod hdr;
+mod isolation;
+mod layers;
+mod letterbox;
+mod links;
+mod matching;
+mod montage;
+#[cfg(feature = "async")]
+mod nonblocking;
+mod orientation;
+mod parallel;
+mod progressive;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "raw")]
+mod raw;
+mod render;
+mod scenes;
+mod session;
+mod sink;
+mod slideshow;
+mod source;
+mod stats;
+mod subcell;
+#[cfg(feature = "svg")]
+mod svg;
+mod temporal;
+mod threshold;
+mod tones;
+mod viewer;
+mod visualizer;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod watermark;
+mod webhook;
+
 use core::fmt;
+use std::{
+    io::{Cursor, Write},
+    iter,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use image::{
+    imageops::FilterType, DynamicImage, GenericImageView, ImageError, ImageFormat, ImageReader,
+    Limits, Rgb, Rgba32FImage, RgbaImage,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use terminal_size::{terminal_size, Height, Width};
+use tones::luminance_histogram;
+use unicode_width::UnicodeWidthChar;
 
-use image::{io::Reader as ImageReader, DynamicImage, GenericImageView, ImageFormat};
-use reqwest::header::CONTENT_TYPE;
+pub use animation::Animation;
+pub use border::Border;
+#[cfg(feature = "http")]
+pub use cache::HttpCache;
+pub use calibration::{calibrate_charset, calibrate_charset_file, sort_charset};
+pub use capabilities::capabilities_json;
+pub use caption::{Caption, CaptionPosition};
+pub use charset::{charset_preset, read_charset_file, Charset, CHARSET_PRESETS};
+pub use color::{load_palette, ColorMode, Palette, PALETTE_PRESETS};
+pub use crop::{Crop, Focus, Length};
+pub use dither::Dither;
+pub use fetch::{default_fetcher, Fetched, Fetcher, NoNetwork};
+#[cfg(feature = "http")]
+pub use fetch::{ReqwestFetcher, RetryPolicy};
+pub use generators::Generator;
+pub use hdr::ToneMapping;
+pub use isolation::{decode_isolated, run_decode_worker, DECODE_WORKER_ARG};
+pub use letterbox::Align;
+pub use links::{read_link_map, LinkMap, LinkRegion};
+pub use matching::GlyphAtlas;
+pub use montage::Montage;
+#[cfg(feature = "async")]
+pub use nonblocking::convert_url;
+pub use orientation::{Flip, Rotation};
+pub use progressive::ProgressiveBudget;
+pub use render::{CellMetrics, OutputFormat};
+pub use session::{read_session, Session};
+pub use sink::{open_sink, AtomicFileSink, ClipboardSink, OutputSink, StdoutSink};
+pub use slideshow::Slideshow;
+pub use source::{
+    DataUriSource, FileSource, ImageSource, ProceduralSource, SourceData, SourceRegistry,
+    StdinSource, UrlSource,
+};
+pub use stats::{cell_statistics, CellStatistics, CELL_STATISTICS_CHANNELS};
+pub use subcell::CellMode;
+pub use threshold::Threshold;
+pub use tones::{load_tone_reference, ToneReference};
+pub use viewer::{run_tuner, run_viewer};
+pub use visualizer::{run_visualizer, VisualizerMode};
+pub use watermark::{load_watermark, Watermark};
+pub use webhook::{post_to_webhook, WebhookService};
 
+#[derive(Debug)]
 pub enum ProgramError {
     InvalidInputPath,
     FailedToDecodeInput,
     FailedToWriteToOutput,
     FailedToDownload,
+    HostUnreachable,
+    DownloadRejected,
     DownloadInvalid,
+    InvalidDataUri,
+    DownloadTimedOut,
+    DownloadTooLarge,
+    InvalidProxy,
+    InvalidHeader,
+    NetworkDisabled,
+    WebhookFailed,
+    FailedToReadCharset,
+    FailedToReadLinkMap,
+    InvalidLinkMap,
+    FailedToReadPalette,
+    InvalidPalette,
+    InvalidLayers,
+    InvalidSession,
+    FailedToReadToneReference,
+    FailedToReadWatermark,
+    EmptyInput,
+    InvalidOutputWidth,
+    OutputTooLarge,
+    EmptyRect,
+    InvalidSymbolAspectRatio,
+    InvalidToneAdjustment,
+    EmptyCharset,
+    DecoderCrashed,
+    DecoderTimedOut,
+    ImageTooLarge,
+    InvalidFont,
+    MissingFont,
+    AudioCaptureFailed,
+    VisualizerUnavailable,
+    ViewerUnavailable,
 }
 
+// Output rows per strip when streaming; a multiple of the 8x8 dither tile so the
+// pattern stays continuous across strips
+const STREAM_STRIP_ROWS: u32 = 16;
+
+// Pixels the image may be resized to at once (cells times their sub-cells), several
+// times a full resolution phone photo in any mode but small enough to fit in memory
+const MAX_SAMPLED_PIXELS: u64 = 1 << 28;
+
+// Share of inverted glyph pairs beyond which a ramp is reported as unsorted; a few
+// near ties are normal for hand made ramps
+const MAX_CHARSET_DISORDER: f32 = 0.15;
+
 #[derive(Debug)]
 pub struct ProgramParameters<'a> {
     pub input_path: &'a str,
     pub output_path: Option<&'a str>,
+    pub isolate_decoding: bool,
+    pub decode_limits: DecodeLimits,
+    pub fetcher: &'a dyn Fetcher,
+    pub sources: &'a SourceRegistry,
+    pub options: ConversionOptions<'a>,
+    pub animation: Option<Animation>,
+    pub animation_duration: Duration,
+    pub rotation: Option<Rotation>,
+    pub flip: Option<Flip>,
+    // Region of the input to convert, applied before resizing
+    pub crop: Option<Crop>,
+    // Cover crop the input to the output area, keeping this part
+    pub focus: Option<Focus>,
+    // Blended into the input before conversion
+    pub watermark: Option<Watermark<'a>>,
+    pub from_rect: Option<Rect>,
+    pub to_rect: Option<Rect>,
+    pub marquee_speed: Option<u32>,
+    pub slideshow: Option<Slideshow>,
+    pub montage: Option<Montage<'a>>,
+    // Thumbnail width in cells of a contact sheet of the input directory
+    pub contact_sheet: Option<u32>,
+    // Where montages and contact sheets list the inputs that failed to convert
+    pub failures_file: Option<&'a str>,
+    // Two inputs shown at the same cell dimensions
+    pub compare: Option<[&'a str; 2]>,
+    // Pad the art with this glyph to exactly fill the output box
+    pub letterbox: Option<char>,
+    pub border: Border,
+    // Position of the art within the terminal's width
+    pub align: Align,
+    // Set into the top edge of the border
+    pub title: Option<&'a str>,
+    // Text written over the art's glyphs
+    pub caption: Option<Caption<'a>>,
+    // Report glyph usage and output sizes on stderr
+    pub stats: bool,
+    // Layers bundle whose glyphs are recolored from the input
+    pub recolor: Option<&'a str>,
+    // The input is a layers bundle to render instead of an image
+    pub from_layers: bool,
+    // Interactive pan and zoom instead of writing the art
+    pub view: bool,
+    // Interactive tuning of the conversion settings, printed as flags on exit
+    pub tune: bool,
+    // Where --view and --tune save the adjustments as a session script
+    pub record_session: Option<&'a str>,
+    pub play: bool,
+    pub playback_fps: Option<u32>,
+    pub interpolate: bool,
+    // Stretch the brightness levels per scene of played animations
+    pub adaptive_levels: bool,
+    // Lower the output width and filter quality until playback keeps up
+    pub auto_tune: bool,
+    pub generator: Option<Generator>,
+    pub stream: bool,
+    // Draw a coarse preview first and refine it in place on the terminal
+    pub progressive: Option<ProgressiveBudget>,
+    pub append: bool,
+    // Written after every frame (and single image) of file output
+    pub frame_separator: Option<&'a str>,
+    // Where --format discord-webhook/slack-webhook output is posted
+    pub webhook_url: Option<&'a str>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConversionOptions<'a> {
     pub output_width: Option<u32>,
     pub symbol_aspect_ratio: f32,
+    pub filter: FilterType,
     pub charset: &'a str,
+    pub luma: Luma,
+    pub background: Rgb<u8>,
+    pub alpha_threshold: Option<f32>,
+    pub transparent_char: char,
+    pub glyph_atlas: Option<&'a GlyphAtlas>,
+    pub format: OutputFormat,
+    pub cell_metrics: CellMetrics,
+    pub font: Option<&'a [u8]>,
+    pub link_map: Option<&'a LinkMap>,
+    pub overlay: bool,
+    pub max_columns: Option<u32>,
+    // Rows the art is fitted into, narrowing it when it would be taller
+    pub max_rows: Option<u32>,
+    pub color: ColorMode,
+    pub palette: Option<&'a Palette>,
+    pub dither: Dither,
+    pub mode: CellMode,
+    pub snap: Option<u32>,
+    // Source pixels each cell samples, overriding the width and aspect correction
+    pub cell_size: Option<(u32, u32)>,
+    // Fixed row count for status bars and prompts; text cells become
+    // background-colored spaces
+    pub oneline_height: Option<u32>,
+    pub tone_reference: Option<&'a ToneReference>,
+    // Index of the frame being converted, which temporal dithering varies with
+    pub frame: u32,
+    // Source brightness range (0..1) stretched over the whole ramp, None for 0..1
+    pub brightness_range: Option<(f32, f32)>,
+    // Applied to the brightness glyphs are picked by, 1 for unchanged; gamma above 1
+    // brightens the midtones
+    pub contrast: f32,
+    pub gamma: f32,
+    // Two glyphs only, split at this brightness
+    pub threshold: Option<Threshold>,
+    // Compresses high dynamic range inputs, after scaling them by `exposure` stops
+    pub tone_mapping: Option<ToneMapping>,
+    pub exposure: f32,
+    // Exposed luminance tone mapping maps to 1, None to measure it on each image
+    // converted
+    pub white_point: Option<f32>,
+}
+
+impl Default for ConversionOptions<'_> {
+    fn default() -> Self {
+        Self {
+            output_width: None,
+            symbol_aspect_ratio: 0.5,
+            filter: FilterType::CatmullRom,
+            charset: " ░▒▓█",
+            luma: Luma::Rec709,
+            background: Rgb([0, 0, 0]),
+            alpha_threshold: None,
+            transparent_char: ' ',
+            glyph_atlas: None,
+            format: OutputFormat::Text,
+            cell_metrics: CellMetrics::default(),
+            font: None,
+            link_map: None,
+            overlay: false,
+            max_columns: None,
+            max_rows: None,
+            color: ColorMode::None,
+            palette: None,
+            dither: Dither::None,
+            mode: CellMode::Glyphs,
+            snap: None,
+            cell_size: None,
+            oneline_height: None,
+            tone_reference: None,
+            frame: 0,
+            brightness_range: None,
+            contrast: 1.0,
+            gamma: 1.0,
+            threshold: None,
+            tone_mapping: None,
+            exposure: 0.0,
+            white_point: None,
+        }
+    }
+}
+
+// Caps on what decoding an (untrusted) input may cost
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    pub max_pixels: Option<u64>,
+    pub max_memory: Option<u64>,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_pixels: Some(100_000_000),
+            max_memory: Some(1024 * 1024 * 1024),
+        }
+    }
 }
 
-enum ImageFormatRes {
-    Invalid,
-    None,
-    Some(ImageFormat),
+impl DecodeLimits {
+    pub fn unlimited() -> Self {
+        Self {
+            max_pixels: None,
+            max_memory: None,
+        }
+    }
+
+    pub(crate) fn check_dimensions(&self, w: u32, h: u32) -> Result<(), ProgramError> {
+        match self.max_pixels {
+            Some(max_pixels) if w as u64 * h as u64 > max_pixels => {
+                Err(ProgramError::ImageTooLarge)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn to_image_limits(self) -> Limits {
+        let mut limits = Limits::no_limits();
+        limits.max_alloc = self.max_memory;
+        limits
+    }
 }
 
+impl ConversionOptions<'_> {
+    // Fixes the tone mapping's white point to the one measured on `img`, so the parts
+    // of it converted separately map alike
+    pub(crate) fn with_white_point(&self, img: &DynamicImage) -> Self {
+        let white_point = match (self.tone_mapping, self.white_point) {
+            (Some(_), None) => Some(hdr::white_point(img, self.exposure, self.luma)),
+            (_, white_point) => white_point,
+        };
+        Self {
+            white_point,
+            ..self.clone()
+        }
+    }
+
+    // Resolves an Otsu threshold to one cutoff over the pooled `histograms` (of the
+    // whole image or sequence), so the strips and frames converted separately split
+    // alike
+    pub(crate) fn with_threshold(
+        &self,
+        histograms: impl IntoIterator<Item = [u64; tones::LEVELS]>,
+    ) -> Self {
+        let threshold = match self.threshold {
+            Some(Threshold::Otsu) => {
+                let mut pooled = [0; tones::LEVELS];
+                for histogram in histograms {
+                    for (total, count) in pooled.iter_mut().zip(histogram) {
+                        *total += count;
+                    }
+                }
+                Some(Threshold::Cutoff(threshold::otsu(&pooled)))
+            }
+            threshold => threshold,
+        };
+        Self {
+            threshold,
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn for_frame(&self, frame: usize) -> Self {
+        Self {
+            frame: frame as u32,
+            ..self.clone()
+        }
+    }
+
+    // Stretches the scene's brightness range over 0..1, then either cuts it to 0 or 1
+    // at the threshold or applies contrast (around mid-gray) and gamma
+    pub(crate) fn adjust_brightness(&self, brightness: f32) -> f32 {
+        let brightness = match self.brightness_range {
+            Some((low, high)) if high > low => (brightness - low) / (high - low),
+            _ => brightness,
+        };
+        if let Some(threshold) = self.threshold {
+            // Otsu is resolved per image before conversion
+            let cutoff = match threshold {
+                Threshold::Cutoff(cutoff) => cutoff,
+                Threshold::Otsu => 0.5,
+            };
+            return if brightness >= cutoff { 1.0 } else { 0.0 };
+        }
+        if self.contrast == 1.0 && self.gamma == 1.0 {
+            return brightness;
+        }
+        let brightness = ((brightness - 0.5) * self.contrast + 0.5).clamp(0.0, 1.0);
+        brightness.powf(1.0 / self.gamma)
+    }
+
+    // Dither offset for the cell at (x, y) when quantizing to `levels` glyphs. None
+    // with a threshold, which must land on the two extremes
+    pub(crate) fn dither_offset(&self, x: u32, y: u32, levels: usize) -> f32 {
+        if self.threshold.is_some() {
+            return 0.0;
+        }
+        self.dither.offset(x, y, self.frame, levels)
+    }
+
+    // Terminal columns spanned by every cell of the art
+    pub(crate) fn cell_width(&self) -> u32 {
+        match self.mode {
+            CellMode::Glyphs | CellMode::Adaptive => {
+                Charset::parse(self.charset).cell_width() as u32
+            }
+            CellMode::Emoji => emoji::EMOJI_CELL_WIDTH,
+            CellMode::HalfBlock | CellMode::Braille | CellMode::Octant => 1,
+        }
+    }
+
+    // Pixels the image is resized to per cell, which depends on how the mode
+    // (or the glyph atlas) samples a cell
+    pub(crate) fn subcell_size(&self) -> (u32, u32) {
+        match self.glyph_atlas {
+            Some(atlas) if self.mode == CellMode::Glyphs => atlas.cell_size(),
+            _ => self.mode.subcells(),
+        }
+    }
+
+    // Rejects parameter combinations that cannot produce any output and returns
+    // warnings for the ones that are clamped to something usable
+    pub fn validate(&self, source_dims: (u32, u32)) -> Result<Vec<String>, ProgramError> {
+        self.validate_resizing(source_dims, None)
+    }
+
+    // `strip_rows` caps the output rows resized at once, for conversions done in
+    // strips, which hold no more than that in memory however tall the art is
+    fn validate_resizing(
+        &self,
+        source_dims: (u32, u32),
+        strip_rows: Option<u32>,
+    ) -> Result<Vec<String>, ProgramError> {
+        if source_dims.0 == 0 || source_dims.1 == 0 {
+            return Err(ProgramError::EmptyInput);
+        }
+        if self.output_width == Some(0)
+            || self.max_columns == Some(0)
+            || self.max_rows == Some(0)
+            || self.cell_size.is_some_and(|(w, h)| w == 0 || h == 0)
+        {
+            return Err(ProgramError::InvalidOutputWidth);
+        }
+        if !(self.symbol_aspect_ratio.is_finite() && self.symbol_aspect_ratio > 0.0) {
+            return Err(ProgramError::InvalidSymbolAspectRatio);
+        }
+        if !(self.contrast.is_finite() && self.contrast >= 0.0)
+            || !(self.gamma.is_finite() && self.gamma > 0.0)
+            || !self.exposure.is_finite()
+        {
+            return Err(ProgramError::InvalidToneAdjustment);
+        }
+        let charset = Charset::parse(self.charset);
+        if charset.glyphs().is_empty() {
+            return Err(ProgramError::EmptyCharset);
+        }
+
+        let mut warnings = Vec::new();
+        if charset.has_mixed_widths() {
+            warnings.push(format!(
+                "charset mixes glyph widths, narrow glyphs are padded to {} columns",
+                charset.cell_width()
+            ));
+        }
+        if self.mode == CellMode::Octant && !subcell::octants_supported(self.font) {
+            warnings.push("font has no octant glyphs, falling back to braille".to_string());
+        }
+        let disorder = calibration::density_disorder(self.charset, self.font);
+        if disorder > MAX_CHARSET_DISORDER {
+            warnings.push(format!(
+                "charset glyphs are out of density order ({:.0}% of pairs inverted), \
+                 --sort-charset reorders them",
+                disorder * 100.0
+            ));
+        }
+        let (w, h) = raw_output_dimensions(source_dims, self);
+        if w > source_dims.0 {
+            warnings.push(format!(
+                "output width {w} exceeds source width {}, the image is upscaled",
+                source_dims.0
+            ));
+        }
+        if h < 1.0 {
+            warnings.push(format!(
+                "output height rounds to 0 rows at width {w}, clamping to 1 row"
+            ));
+        }
+        let (columns, rows) = output_dimensions(source_dims, self);
+        let rows = strip_rows.map_or(rows, |strip_rows| strip_rows.min(rows));
+        let (sub_w, sub_h) = self.subcell_size();
+        if columns as u64 * sub_w as u64 * rows as u64 * sub_h as u64 > MAX_SAMPLED_PIXELS {
+            return Err(ProgramError::OutputTooLarge);
+        }
+        if self
+            .alpha_threshold
+            .is_some_and(|threshold| !(0.0..=1.0).contains(&threshold))
+        {
+            warnings.push("alpha threshold outside 0..1, clamping".to_string());
+        }
+        if self.format == OutputFormat::Ans && w * self.cell_width() > 80 {
+            warnings.push(format!(
+                "ANSI art wider than 80 columns ({w}) needs a viewer that honors SAUCE widths"
+            ));
+        }
+        if self.overlay && self.alpha_threshold.is_none() {
+            warnings
+                .push("overlay has no transparent cells without an alpha threshold".to_string());
+        }
+        Ok(warnings)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArtOutput {
+    pub art: Vec<u8>,
+    pub source_dims: (u32, u32),
+    pub output_dims: (u32, u32),
+    pub bytes_len: usize,
+    pub duration: Duration,
+    pub warnings: Vec<String>,
+}
+
+impl ArtOutput {
+    // None for binary output formats such as PNG
+    pub fn as_text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.art).ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Luma {
+    Rec709,
+    Rec601,
+}
+
+impl Luma {
+    fn weights(self) -> (f32, f32, f32) {
+        match self {
+            Luma::Rec709 => (0.2126, 0.7152, 0.0722),
+            Luma::Rec601 => (0.299, 0.587, 0.114),
+        }
+    }
+}
+
+impl FromStr for Luma {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rec709" | "709" => Ok(Self::Rec709),
+            "rec601" | "601" => Ok(Self::Rec601),
+            _ => Err(format!("unknown luma formula: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    fn lerp(self, other: Rect, t: f32) -> Rect {
+        let mix = |a: u32, b: u32| (a as f32 + (b as f32 - a as f32) * t).round() as u32;
+        Rect {
+            x: mix(self.x, other.x),
+            y: mix(self.y, other.y),
+            width: mix(self.width, other.width).max(1),
+            height: mix(self.height, other.height).max(1),
+        }
+    }
+
+    // The part of the rect inside a (w, h) image, None when they don't overlap
+    fn intersect(self, (w, h): (u32, u32)) -> Option<Rect> {
+        let x1 = self.x.saturating_add(self.width).min(w);
+        let y1 = self.y.saturating_add(self.height).min(h);
+        (self.x < x1 && self.y < y1).then(|| Rect {
+            x: self.x,
+            y: self.y,
+            width: x1 - self.x,
+            height: y1 - self.y,
+        })
+    }
+
+    // Grows the rect around its center to `aspect` (width / height), staying inside a
+    // (w, h) image, so its crop isn't stretched when resized to a grid of that aspect
+    fn expand_to_aspect(self, aspect: f32, (w, h): (u32, u32)) -> Rect {
+        let (mut width, mut height) = (self.width as f32, self.height as f32);
+        if width < height * aspect {
+            width = height * aspect;
+        } else {
+            height = width / aspect;
+        }
+        if width > w as f32 {
+            (width, height) = (w as f32, w as f32 / aspect);
+        }
+        if height > h as f32 {
+            (width, height) = (h as f32 * aspect, h as f32);
+        }
+        let width = (width.round() as u32).clamp(1, w);
+        let height = (height.round() as u32).clamp(1, h);
+        let place = |start: u32, size: u32, expanded: u32, bound: u32| {
+            let center = start as f32 + size as f32 / 2.0;
+            ((center - expanded as f32 / 2.0).round().max(0.0) as u32).min(bound - expanded)
+        };
+        Rect {
+            x: place(self.x, self.width, width, w),
+            y: place(self.y, self.height, height, h),
+            width,
+            height,
+        }
+    }
+
+    fn clamp_to(self, (w, h): (u32, u32)) -> Rect {
+        let x = self.x.min(w.saturating_sub(1));
+        let y = self.y.min(h.saturating_sub(1));
+        Rect {
+            x,
+            y,
+            width: self.width.min(w - x).max(1),
+            height: self.height.min(h - y).max(1),
+        }
+    }
+}
+
+impl FromStr for Rect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<u32> = s
+            .split(',')
+            .map(|v| v.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("invalid rectangle: {s}"))?;
+        match values[..] {
+            [x, y, width, height] => Ok(Rect {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(format!("invalid rectangle (expected x,y,w,h): {s}")),
+        }
+    }
+}
+
+#[derive(Clone)]
 struct AsciiImage {
     dimensions: (u32, u32),
-    data: Vec<Vec<char>>,
+    data: Vec<Vec<String>>,
+    // Cells below the alpha threshold, which overlay exports leave untouched
+    transparent: Vec<Vec<bool>>,
+    // Average source color of each cell, for exports that support color
+    colors: Vec<Vec<Rgb<u8>>>,
 }
 
 impl fmt::Display for AsciiImage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for line in &self.data {
-            for c in line {
-                write!(f, "{c}")?;
-            }
-            writeln!(f)?;
-        }
-        Ok(())
+        f.write_str(&self.to_text())
     }
 }
 
@@ -47,112 +708,946 @@ impl AsciiImage {
     pub fn create_empty(dimensions: (u32, u32)) -> Self {
         Self {
             dimensions,
-            data: vec![vec!['.'; dimensions.0 as usize]; dimensions.1 as usize],
+            data: vec![vec![String::from("."); dimensions.0 as usize]; dimensions.1 as usize],
+            transparent: vec![vec![false; dimensions.0 as usize]; dimensions.1 as usize],
+            colors: vec![vec![Rgb([u8::MAX; 3]); dimensions.0 as usize]; dimensions.1 as usize],
+        }
+    }
+
+    // Spaces as wide as the charset's cells, for canvases other art is placed on
+    fn create_blank(dimensions: (u32, u32), cell_width: u32) -> Self {
+        Self::create_filled(dimensions, &" ".repeat(cell_width as usize))
+    }
+
+    fn create_filled(dimensions: (u32, u32), glyph: &str) -> Self {
+        let mut image = Self::create_empty(dimensions);
+        for cell in image.data.iter_mut().flatten() {
+            cell.clear();
+            cell.push_str(glyph);
+        }
+        image
+    }
+
+    // Copies `other` with its top-left cell at `offset`, clipping what falls outside
+    fn blit(&mut self, other: &AsciiImage, (x, y): (u32, u32)) {
+        let (x, y) = (x as usize, y as usize);
+        for (sy, row) in other.data.iter().enumerate() {
+            let ty = y + sy;
+            if ty >= self.data.len() {
+                break;
+            }
+            for (sx, cell) in row.iter().enumerate() {
+                let tx = x + sx;
+                if tx >= self.data[ty].len() {
+                    break;
+                }
+                self.data[ty][tx].clone_from(cell);
+                self.transparent[ty][tx] = other.transparent[sy][sx];
+                self.colors[ty][tx] = other.colors[sy][sx];
+            }
+        }
+    }
+
+    // Writes one character per cell from `offset`, cut to `width` cells
+    fn write_label(&mut self, label: &str, (x, y): (u32, u32), width: u32, color: Rgb<u8>) {
+        let y = y as usize;
+        let Some(row) = self.data.get_mut(y) else {
+            return;
+        };
+        for (tx, c) in (x as usize..).zip(label.chars().take(width as usize)) {
+            let Some(cell) = row.get_mut(tx) else {
+                break;
+            };
+            // Padded to the (blank) cell's width so columns stay aligned
+            let padding = cell.len().saturating_sub(c.width().unwrap_or(1));
+            *cell = format!("{c}{}", " ".repeat(padding));
+            self.transparent[y][tx] = false;
+            self.colors[y][tx] = color;
+        }
+    }
+
+    // Replaces the cells from `offset` onwards with `cells`, clipping what falls outside
+    fn overlay(&mut self, cells: &[String], (x, y): (u32, u32), color: Rgb<u8>) {
+        let y = y as usize;
+        let Some(row) = self.data.get_mut(y) else {
+            return;
+        };
+        for (tx, cell) in (x as usize..).zip(cells) {
+            let Some(target) = row.get_mut(tx) else {
+                break;
+            };
+            target.clone_from(cell);
+            self.transparent[y][tx] = false;
+            self.colors[y][tx] = color;
+        }
+    }
+
+    // Exact byte length of the plain text rendering, so buffers are allocated once
+    fn text_len(&self) -> usize {
+        self.data
+            .iter()
+            .map(|line| line.iter().map(String::len).sum::<usize>() + 1)
+            .sum()
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::with_capacity(self.text_len());
+        for line in &self.data {
+            for cell in line {
+                text.push_str(cell);
+            }
+            text.push('\n');
         }
+        text
     }
 
-    pub fn create_from(img: &DynamicImage, charset: &str) -> Self {
+    pub fn create_from(img: &DynamicImage, options: &ConversionOptions) -> Self {
         let mut ascii_img = Self::create_empty(img.dimensions());
-        ascii_img.copy_from(img, charset);
+        ascii_img.copy_from(img, options);
         ascii_img
     }
 
-    pub fn copy_from(&mut self, img: &DynamicImage, charset: &str) {
+    pub fn copy_from(&mut self, img: &DynamicImage, options: &ConversionOptions) {
         assert!(img.dimensions() == self.dimensions);
-        for y in 0..self.dimensions.1 {
-            for x in 0..self.dimensions.0 {
-                let pixel = img.get_pixel(x, y);
-                let brightness =
-                    (pixel[0] as f32 / u8::MAX as f32) * (pixel[3] as f32 / u8::MAX as f32);
-                let num_chars = charset.chars().count();
-                let symbol = charset
-                    .chars()
-                    .nth(brightness_to_index(brightness, num_chars))
-                    .unwrap();
-                self.data[y as usize][x as usize] = symbol;
+        let charset = Charset::parse(options.charset);
+        // Full precision for 16-bit and float inputs
+        let img = img.to_rgba32f();
+        let rows = parallel::map_ordered((0..self.dimensions.1).collect(), |y| {
+            Self::convert_row(&img, y, &charset, options)
+        });
+        for (y, row) in rows.into_iter().enumerate() {
+            self.set_row(y, row);
+        }
+    }
+
+    fn set_row(&mut self, y: usize, row: AsciiRow) {
+        self.data[y] = row.cells;
+        self.transparent[y] = row.transparent;
+        self.colors[y] = row.colors;
+    }
+
+    // Rows only depend on their own pixels, so they can be converted in parallel
+    fn convert_row(
+        img: &Rgba32FImage,
+        y: u32,
+        charset: &Charset,
+        options: &ConversionOptions,
+    ) -> AsciiRow {
+        let (wr, wg, wb) = options.luma.weights();
+        let alpha_threshold = options.alpha_threshold.map(|t| t.clamp(0.0, 1.0));
+        let width = img.width();
+        let mut row = AsciiRow {
+            cells: Vec::with_capacity(width as usize),
+            transparent: Vec::with_capacity(width as usize),
+            colors: Vec::with_capacity(width as usize),
+        };
+        for x in 0..width {
+            let pixel = img.get_pixel(x, y);
+            let transparent = alpha_threshold.is_some_and(|threshold| pixel[3] < threshold);
+            let color = Rgb([quantize(pixel[0]), quantize(pixel[1]), quantize(pixel[2])]);
+            row.transparent.push(transparent);
+            row.colors.push(color);
+            if transparent {
+                let transparent_char = options.transparent_char.encode_utf8(&mut [0; 4]);
+                row.cells.push(match options.mode {
+                    CellMode::Emoji => format!("{transparent_char:<2}"),
+                    _ => charset.pad(transparent_char),
+                });
+                continue;
             }
+            if options.mode == CellMode::Emoji {
+                row.cells.push(emoji::nearest_emoji(color).to_string());
+                continue;
+            }
+            let luminance = wr * pixel[0] + wg * pixel[1] + wb * pixel[2];
+            let brightness = options.adjust_brightness(luminance)
+                + options.dither_offset(x, y, charset.glyphs().len());
+            row.cells.push(charset.glyph_for(brightness).to_string());
         }
+        row
     }
 }
 
+struct AsciiRow {
+    cells: Vec<String>,
+    transparent: Vec<bool>,
+    colors: Vec<Rgb<u8>>,
+}
+
 pub fn generate_image(params: &ProgramParameters) -> Result<(), ProgramError> {
     // let pp = PathBuf::from("http://seznam.cz/image.png");
     // image::ImageFormat::from_mime_type(mime_type)
     // println!("{:?}", pp.extension());
     // image::load_from_memory_with_format(&[0u8;1], image::ImageFormat::from_extension(ext));
-    let img = load_image(params.input_path)?;
+    if params.play {
+        return play_animated_input(params);
+    }
+    if let Some(generator) = params.generator {
+        return play_generated(params, generator);
+    }
+    if let Some(slideshow) = params.slideshow {
+        return slideshow::run(params, slideshow);
+    }
+    if params.from_layers {
+        return compose_layers(params);
+    }
+    if let Some(montage) = &params.montage {
+        return montage::run(params, montage);
+    }
+    if let Some(thumbnail_cells) = params.contact_sheet {
+        return montage::contact_sheet(params, thumbnail_cells);
+    }
+    if let Some(inputs) = params.compare {
+        return montage::compare(params, inputs);
+    }
+    if let Some(bundle) = params.recolor {
+        return recolor(params, bundle);
+    }
+
+    let img = load_image(params)?;
+    let boxed = params
+        .letterbox
+        .map(|_| letterbox::box_options(&params.options));
+    let options = boxed.as_ref().unwrap_or(&params.options);
+
+    if params.view {
+        return run_viewer(&img, options, params.record_session);
+    }
+    if params.tune {
+        return run_tuner(&img, options, params.record_session);
+    }
+    if params.stream {
+        return stream_to_output(params, &img);
+    }
 
     let (orig_w, orig_h) = img.dimensions();
-    let aspect_ratio = orig_w as f32 / orig_h as f32;
+    for warning in options.validate((orig_w, orig_h))? {
+        eprintln!("Warning: {warning}");
+    }
+    let dimensions = output_dimensions((orig_w, orig_h), options);
+    let img = match_tones(composite_over_background(&img, options), options);
+    let options =
+        &options.with_threshold(iter::once_with(|| luminance_histogram(&img, options.luma)));
+
+    if params.animation == Some(Animation::KenBurns) {
+        let full = Rect {
+            x: 0,
+            y: 0,
+            width: orig_w,
+            height: orig_h,
+        };
+        let from = params.from_rect.unwrap_or(full);
+        // The middle quarter, at least a pixel for tiny images
+        let to = params.to_rect.unwrap_or(Rect {
+            x: orig_w / 4,
+            y: orig_h / 4,
+            width: (orig_w / 2).max(1),
+            height: (orig_h / 2).max(1),
+        });
+        let (Some(from), Some(to)) = (
+            from.intersect((orig_w, orig_h)),
+            to.intersect((orig_w, orig_h)),
+        ) else {
+            return Err(ProgramError::EmptyRect);
+        };
+        let frame_count = animation::frame_count(params.animation_duration);
+        let mut frames = animation::ken_burns_frames(&img, from, to, frame_count, |i, frame| {
+            resize_and_convert(frame, dimensions, &options.for_frame(i))
+        });
+        temporal::persist(&mut frames, options);
+        write_frames(params, &frames, animation::frame_delay())?;
+        return Ok(());
+    }
+
+    let ascii_image = match params.progressive {
+        Some(budget) => progressive::run(&img, dimensions, options, budget)?,
+        None => resize_and_convert(&img, dimensions, options),
+    };
+    let ascii_image = match params.letterbox {
+        Some(fill) => letterbox::letterbox(&ascii_image, options, fill),
+        None => ascii_image,
+    };
+    let ascii_image = caption::overlay(
+        ascii_image,
+        params.caption.as_ref(),
+        options.cell_width(),
+        montage::ink(options.background),
+    );
+    let ascii_image = border::frame(
+        ascii_image,
+        params.border,
+        params.title,
+        options.cell_width(),
+        montage::ink(options.background),
+    );
+    let ascii_image = letterbox::align(ascii_image, params.align, options.cell_width());
+    if let Some(speed) = params.marquee_speed {
+        let frames =
+            animation::marquee_frames(&ascii_image, terminal_width() / options.cell_width());
+        write_frames(params, &frames, Duration::from_secs(1) / speed.max(1))?;
+    } else if let Some(animation) = params.animation {
+        let frame_count = animation::frame_count(params.animation_duration);
+        let frames = animation::generate_frames(&ascii_image, animation, frame_count);
+        write_frames(params, &frames, animation::frame_delay())?;
+    } else if params.progressive.is_none() {
+        write_art(params, options, &ascii_image)?;
+    }
+    if params.stats {
+        eprint!("{}", stats::art_report(&ascii_image, options));
+    }
 
-    let w = params.output_width.unwrap_or(orig_w);
-    let ascii_art_height = (w as f32 * params.symbol_aspect_ratio / aspect_ratio) as u32;
+    Ok(())
+}
+
+// Renders an (edited) --format layers bundle in the requested format
+fn compose_layers(params: &ProgramParameters) -> Result<(), ProgramError> {
+    let (art, background) = layers::parse_layers(&read_input_bytes(params)?)?;
+    let options = ConversionOptions {
+        background,
+        ..params.options.clone()
+    };
+    write_art(params, &options, &art)
+}
+
+// Keeps the glyphs (and transparency) of a --format layers bundle, taking the colors
+// from the input resampled to the bundle's grid
+fn recolor(params: &ProgramParameters, bundle: &str) -> Result<(), ProgramError> {
+    let bytes = std::fs::read(bundle).map_err(|_| ProgramError::InvalidLayers)?;
+    let (mut art, background) = layers::parse_layers(&bytes)?;
+    let options = ConversionOptions {
+        background,
+        ..params.options.clone()
+    };
+    let (w, h) = art.dimensions;
+    let img = match_tones(
+        composite_over_background(&load_image(params)?, &options),
+        &options,
+    );
+    let colors = img.resize_exact(w, h, options.filter).to_rgb8();
+    for (row, colors_row) in art.colors.iter_mut().zip(colors.rows()) {
+        for (color, pixel) in row.iter_mut().zip(colors_row) {
+            *color = *pixel;
+        }
+    }
+    write_art(params, &options, &art)
+}
 
-    let img2 =
-        img.grayscale()
-            .resize_exact(w, ascii_art_height, image::imageops::FilterType::CatmullRom);
+fn play_animated_input(params: &ProgramParameters) -> Result<(), ProgramError> {
+    let options = &params.options;
+    let bytes = read_input_bytes(params)?;
+    let frames = animation::decode_gif_frames(&bytes, params.decode_limits)?;
+    let Some((first, _)) = frames.first() else {
+        return Err(ProgramError::EmptyInput);
+    };
 
-    let ascii_image = AsciiImage::create_from(&img2, params.charset);
-    if let Some(output_path) = params.output_path {
-        std::fs::write(output_path, ascii_image.to_string())
-            .map_err(|_| ProgramError::FailedToWriteToOutput)?;
+    for warning in options.validate(first.dimensions())? {
+        eprintln!("Warning: {warning}");
+    }
+    let fps = params
+        .playback_fps
+        .unwrap_or(animation::FRAMES_PER_SECOND)
+        .max(1);
+    // 8-bit frames can't outshine SDR white, so the first frame's white point holds
+    // for the whole sequence and it doesn't flicker
+    let options = &realtime_options(params, first, fps)
+        .with_white_point(&DynamicImage::ImageRgba8(first.clone()));
+    let dimensions = output_dimensions(first.dimensions(), options);
+
+    let frames = animation::resample_frames(&frames, fps, params.interpolate);
+    let options = &options.with_threshold(
+        frames
+            .iter()
+            .map(|frame| luminance_histogram(frame, options.luma)),
+    );
+    let ranges = scene_ranges(params, &frames);
+    let mut ascii_frames =
+        parallel::map_ordered(frames.into_iter().enumerate().collect(), |(i, frame)| {
+            let img = composite_over_background(&DynamicImage::ImageRgba8(frame), options);
+            let options = scene_options(options, &ranges, i);
+            resize_and_convert(&match_tones(img, &options), dimensions, &options)
+        });
+    temporal::persist(&mut ascii_frames, options);
+    write_frames(params, &ascii_frames, Duration::from_secs(1) / fps)
+}
+
+// Options tuned to sustain `fps` on this machine and terminal, when requested and
+// playing to the terminal
+fn realtime_options<'a>(
+    params: &ProgramParameters<'a>,
+    sample: &RgbaImage,
+    fps: u32,
+) -> ConversionOptions<'a> {
+    if params.auto_tune && params.output_path.is_none() {
+        let sample = DynamicImage::ImageRgba8(sample.clone());
+        autotune::auto_tune(&sample, &params.options, fps)
     } else {
-        println!("{ascii_image}");
+        params.options.clone()
+    }
+}
+
+// Brightness range of every frame's scene, empty unless adaptive levels are on
+fn scene_ranges(params: &ProgramParameters, frames: &[RgbaImage]) -> Vec<(f32, f32)> {
+    if params.adaptive_levels {
+        scenes::brightness_ranges(frames, params.options.luma)
+    } else {
+        Vec::new()
+    }
+}
+
+fn scene_options<'a>(
+    options: &ConversionOptions<'a>,
+    ranges: &[(f32, f32)],
+    frame: usize,
+) -> ConversionOptions<'a> {
+    let mut options = options.for_frame(frame);
+    if let Some(&range) = ranges.get(frame) {
+        options.brightness_range = Some(range);
     }
+    options
+}
 
+fn stream_to_output(params: &ProgramParameters, img: &DynamicImage) -> Result<(), ProgramError> {
+    let mut sink = sink::open_sink(params.output_path, params.append)?;
+    let output = stream_image(img, &params.options, &mut sink)?;
+    sink.write_all(params.frame_separator.unwrap_or_default().as_bytes())
+        .and_then(|_| sink.finish())
+        .map_err(|_| ProgramError::FailedToWriteToOutput)?;
+    for warning in output.warnings {
+        eprintln!("Warning: {warning}");
+    }
     Ok(())
 }
 
-fn brightness_to_index(brightness: f32, num_chars: usize) -> usize {
-    (brightness * num_chars as f32 - 0.5)
-        .round()
-        .clamp(0.0, num_chars as f32 - 1.0) as usize
+fn play_generated(params: &ProgramParameters, generator: Generator) -> Result<(), ProgramError> {
+    let options = &params.options;
+    let frame_count = animation::frame_count(params.animation_duration);
+    let frames = generators::generate_frames(generator, frame_count);
+    let Some(first) = frames.first() else {
+        return Err(ProgramError::EmptyInput);
+    };
+
+    for warning in options.validate(first.dimensions())? {
+        eprintln!("Warning: {warning}");
+    }
+    let options = &realtime_options(params, first, animation::FRAMES_PER_SECOND).with_threshold(
+        frames
+            .iter()
+            .map(|frame| luminance_histogram(frame, params.options.luma)),
+    );
+    let dimensions = output_dimensions(first.dimensions(), options);
+    let ranges = scene_ranges(params, &frames);
+    let mut ascii_frames =
+        parallel::map_ordered(frames.into_iter().enumerate().collect(), |(i, frame)| {
+            let options = scene_options(options, &ranges, i);
+            let img = match_tones(DynamicImage::ImageRgba8(frame), &options);
+            resize_and_convert(&img, dimensions, &options)
+        });
+    temporal::persist(&mut ascii_frames, options);
+    write_frames(params, &ascii_frames, animation::frame_delay())
+}
+
+pub fn convert_image(
+    img: &DynamicImage,
+    options: &ConversionOptions,
+) -> Result<ArtOutput, ProgramError> {
+    let start = Instant::now();
+    let source_dims = img.dimensions();
+    let warnings = options.validate(source_dims)?;
+    let output_dims = output_dimensions(source_dims, options);
+
+    let img = match_tones(composite_over_background(img, options), options);
+    let art = render::renderer(options)?.render(&resize_and_convert(&img, output_dims, options));
+    Ok(ArtOutput {
+        bytes_len: art.len(),
+        art,
+        source_dims,
+        output_dims,
+        duration: start.elapsed(),
+        warnings,
+    })
 }
 
-fn load_image(path: &str) -> Result<DynamicImage, ProgramError> {
-    if path.starts_with("http://") || path.starts_with("https://") {
-        load_image_from_url(path)
+// Converts the `region` of `img` (clamped to the image) at `scale` output columns
+// per source pixel, for viewers that pan and zoom over a large image
+pub fn convert_region(
+    img: &DynamicImage,
+    region: Rect,
+    scale: f32,
+    options: &ConversionOptions,
+) -> Result<ArtOutput, ProgramError> {
+    let region = region.clamp_to(img.dimensions());
+    let crop = img.crop_imm(region.x, region.y, region.width, region.height);
+    let options = ConversionOptions {
+        output_width: Some(((region.width as f32 * scale).round() as u32).max(1)),
+        ..options.clone()
+    };
+    convert_image(&crop, &options)
+}
+
+// Streams the art row by row as plain text lines (whatever the output format) so
+// embedders can forward them before the whole image is converted. The image is
+// resized and converted in horizontal strips, so only one strip of the resized
+// image and of the char grid is held at a time. The returned output carries no
+// art, its bytes_len counts the streamed line bytes.
+pub fn convert_image_with(
+    img: &DynamicImage,
+    options: &ConversionOptions,
+    mut on_row: impl FnMut(u32, &str),
+) -> Result<ArtOutput, ProgramError> {
+    let start = Instant::now();
+    let source_dims = img.dimensions();
+    let warnings = options.validate_resizing(source_dims, Some(STREAM_STRIP_ROWS))?;
+    let output_dims = output_dimensions(source_dims, options);
+    let (src_w, src_h) = source_dims;
+    let (w, h) = output_dims;
+
+    // Tones are matched, tone mapped and thresholded against the whole image, not
+    // strip by strip; the threshold histogram is gathered in bands of rows, as
+    // converting the whole image at once is what streaming avoids
+    let options = &options.with_white_point(img);
+    let options =
+        &options.with_threshold((0..src_h).step_by(STREAM_STRIP_ROWS as usize).map(|y| {
+            let band = img.crop_imm(0, y, src_w, STREAM_STRIP_ROWS.min(src_h - y));
+            luminance_histogram(&composite_over_background(&band, options), options.luma)
+        }));
+    let tone_map = options
+        .tone_reference
+        .map(|reference| reference.tone_map(img, options.luma));
+    let mut bytes_len = 0;
+    for strip_start in (0..h).step_by(STREAM_STRIP_ROWS as usize) {
+        let rows = STREAM_STRIP_ROWS.min(h - strip_start);
+        // Source rows covered by the strip; resizing strips separately can differ
+        // from a whole-image resize by a pixel or so at strip edges
+        let y0 = (strip_start as u64 * src_h as u64 / h as u64) as u32;
+        let y1 = ((strip_start + rows) as u64 * src_h as u64).div_ceil(h as u64) as u32;
+        let y1 = y1.clamp(y0 + 1, src_h);
+        let strip = composite_over_background(&img.crop_imm(0, y0, src_w, y1 - y0), options);
+        let strip = match &tone_map {
+            Some(tone_map) => tone_map.apply(&strip),
+            None => strip,
+        };
+        let ascii_strip = resize_and_convert(&strip, (w, rows), options);
+        for (i, line) in ascii_strip.data.iter().enumerate() {
+            let line = line.concat();
+            bytes_len += line.len();
+            on_row(strip_start + i as u32, &line);
+        }
+    }
+
+    Ok(ArtOutput {
+        art: Vec::new(),
+        bytes_len,
+        source_dims,
+        output_dims,
+        duration: start.elapsed(),
+        warnings,
+    })
+}
+
+// Writes the art as plain text lines while it's being converted
+pub fn stream_image(
+    img: &DynamicImage,
+    options: &ConversionOptions,
+    out: &mut dyn Write,
+) -> Result<ArtOutput, ProgramError> {
+    let mut written = Ok(());
+    let output = convert_image_with(img, options, |_, line| {
+        if written.is_ok() {
+            written = writeln!(out, "{line}");
+        }
+    })?;
+    written.map_err(|_| ProgramError::FailedToWriteToOutput)?;
+    Ok(output)
+}
+
+pub fn convert_bytes(
+    bytes: &[u8],
+    format: Option<ImageFormat>,
+    options: &ConversionOptions,
+) -> Result<ArtOutput, ProgramError> {
+    let start = Instant::now();
+    let img = decode_encoded(bytes, format, None, options, false, DecodeLimits::default())?;
+    let mut output = convert_image(&img, options)?;
+    output.duration = start.elapsed();
+    Ok(output)
+}
+
+// Wide glyphs span several terminal columns, so the requested width (in columns)
+// holds fewer of them and each one covers a proportionally wider area
+fn raw_output_dimensions(source: (u32, u32), options: &ConversionOptions) -> (u32, f32) {
+    let (orig_w, orig_h) = source;
+    let aspect_ratio = orig_w as f32 / orig_h as f32;
+    let cell_width = options.cell_width();
+
+    // Status lines span the terminal no matter how tall the image is
+    let columns = match options.oneline_height {
+        Some(_) => options.output_width.unwrap_or_else(terminal_width),
+        None => options.output_width.unwrap_or(orig_w),
+    };
+    let columns = options.max_columns.map_or(columns, |max| columns.min(max));
+    let w = columns / cell_width;
+    if let Some(rows) = options.oneline_height {
+        return (w, rows as f32);
+    }
+    if let Some((cell_w, cell_h)) = options.cell_size {
+        // Scaled down as a whole to respect the limits, keeping the cells' proportions
+        let (w, h) = (orig_w.div_ceil(cell_w), orig_h.div_ceil(cell_h));
+        let max_w = options.max_columns.map_or(w, |max| max / cell_width);
+        let max_h = options.max_rows.unwrap_or(h);
+        let scale = (max_w as f32 / w as f32)
+            .min(max_h as f32 / h as f32)
+            .min(1.0);
+        return ((w as f32 * scale) as u32, h as f32 * scale);
+    }
+    let symbol_aspect_ratio = options.symbol_aspect_ratio * cell_width as f32;
+    let h = w as f32 * symbol_aspect_ratio / aspect_ratio;
+    match options.max_rows {
+        Some(rows) if h > rows as f32 => (
+            (rows as f32 * aspect_ratio / symbol_aspect_ratio) as u32,
+            rows as f32,
+        ),
+        _ => (w, h),
+    }
+}
+
+fn output_dimensions(source: (u32, u32), options: &ConversionOptions) -> (u32, u32) {
+    let (w, ascii_art_height) = raw_output_dimensions(source, options);
+    let dimensions = (w.max(1), (ascii_art_height as u32).max(1));
+    match options.snap {
+        Some(multiple) if multiple > 1 => snap_dimensions(dimensions, multiple, options),
+        _ => dimensions,
+    }
+}
+
+// Rounds the cell grid so the sampled pixel grid (cells x sub-cells) is a
+// multiple of `multiple` in both directions, without exceeding max_columns
+fn snap_dimensions(
+    dimensions: (u32, u32),
+    multiple: u32,
+    options: &ConversionOptions,
+) -> (u32, u32) {
+    let (sub_w, sub_h) = options.subcell_size();
+    let step = |subcells: u32| multiple / gcd(multiple, subcells);
+    let round = |cells: u32, step: u32| ((cells + step / 2) / step).max(1) * step;
+
+    let (step_w, step_h) = (step(sub_w), step(sub_h));
+    let mut w = round(dimensions.0, step_w);
+    let cell_width = options.cell_width();
+    while w > step_w && options.max_columns.is_some_and(|max| w * cell_width > max) {
+        w -= step_w;
+    }
+    (w, round(dimensions.1, step_h))
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
     } else {
-        load_image_from_file(path)
+        gcd(b, a % b)
     }
 }
 
-fn load_image_from_url(path: &str) -> Result<DynamicImage, ProgramError> {
-    let x = reqwest::blocking::get(path).map_err(|_| ProgramError::FailedToDownload)?;
-    match get_image_format_from_response(&x) {
-        ImageFormatRes::Invalid => Err(ProgramError::DownloadInvalid),
-        ImageFormatRes::None => {
-            let bytes = x.bytes().map_err(|_| ProgramError::DownloadInvalid)?;
-            image::load_from_memory(&bytes).map_err(|_| ProgramError::DownloadInvalid)
+fn resize_and_convert(
+    img: &DynamicImage,
+    dimensions: (u32, u32),
+    options: &ConversionOptions,
+) -> AsciiImage {
+    let (sub_w, sub_h) = options.subcell_size();
+    let img2 = img.resize_exact(dimensions.0 * sub_w, dimensions.1 * sub_h, options.filter);
+    // Conversions made of several strips or frames resolve Otsu beforehand
+    let options =
+        &options.with_threshold(iter::once_with(|| luminance_histogram(&img2, options.luma)));
+    match (options.mode, options.glyph_atlas) {
+        (CellMode::HalfBlock | CellMode::Braille | CellMode::Octant | CellMode::Adaptive, _) => {
+            subcell::convert_subcells(&img2, dimensions, options)
         }
-        ImageFormatRes::Some(format) => {
-            let bytes = x.bytes().map_err(|_| ProgramError::DownloadInvalid)?;
-            image::load_from_memory_with_format(&bytes, format)
-                .map_err(|_| ProgramError::DownloadInvalid)
+        (CellMode::Glyphs, Some(atlas)) => {
+            matching::match_glyphs(&img2, dimensions, options, atlas)
         }
+        _ => AsciiImage::create_from(&img2, options),
+    }
+}
+
+fn write_frames(
+    params: &ProgramParameters,
+    frames: &[AsciiImage],
+    frame_delay: Duration,
+) -> Result<(), ProgramError> {
+    if params.output_path.is_some() {
+        let renderer = render::renderer(&params.options)?;
+        let separator = params.frame_separator.unwrap_or_default().as_bytes();
+        // Binary formats can't be concatenated and chats shouldn't be flooded, so
+        // they only keep the final frame
+        let format = params.options.format;
+        let frames = if format.is_binary() || format.webhook().is_some() {
+            &frames[frames.len().saturating_sub(1)..]
+        } else {
+            frames
+        };
+        let mut output = Vec::new();
+        for frame in frames {
+            output.extend(renderer.render(frame));
+            output.extend(separator);
+        }
+        write_output(params, &output)
+    } else {
+        animation::play(frames, frame_delay, &params.options)
+    }
+}
+
+// A single (not animated) image, newline terminated on the terminal
+fn write_art(
+    params: &ProgramParameters,
+    options: &ConversionOptions,
+    art: &AsciiImage,
+) -> Result<(), ProgramError> {
+    let mut output = render::renderer(options)?.render(art);
+    if matches!(params.output_path, None | Some("-")) && !options.format.is_binary() {
+        output.push(b'\n');
+    }
+    output.extend(params.frame_separator.unwrap_or_default().as_bytes());
+    write_output(params, &output)
+}
+
+fn write_output(params: &ProgramParameters, bytes: &[u8]) -> Result<(), ProgramError> {
+    if let (Some(service), Some(url)) = (params.options.format.webhook(), params.webhook_url) {
+        return post_to_webhook(
+            params.fetcher,
+            url,
+            service,
+            &String::from_utf8_lossy(bytes),
+        );
+    }
+    let mut sink = sink::open_sink(params.output_path, params.append)?;
+    sink.write_all(bytes)
+        .and_then(|_| sink.finish())
+        .map_err(|_| ProgramError::FailedToWriteToOutput)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn terminal_width() -> u32 {
+    terminal_size().map(|(Width(w), _)| w as u32).unwrap_or(80)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn terminal_height() -> u32 {
+    terminal_size().map(|(_, Height(h))| h as u32).unwrap_or(24)
+}
+
+// There's no terminal to measure in the browser
+#[cfg(target_arch = "wasm32")]
+fn terminal_width() -> u32 {
+    80
+}
+
+#[cfg(target_arch = "wasm32")]
+fn terminal_height() -> u32 {
+    24
+}
+
+pub fn parse_filter(s: &str) -> Result<FilterType, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "nearest" => Ok(FilterType::Nearest),
+        "triangle" => Ok(FilterType::Triangle),
+        "catmullrom" => Ok(FilterType::CatmullRom),
+        "gaussian" => Ok(FilterType::Gaussian),
+        "lanczos3" => Ok(FilterType::Lanczos3),
+        _ => Err(format!("unknown resize filter: {s}")),
     }
 }
 
-fn load_image_from_file(path: &str) -> Result<DynamicImage, ProgramError> {
-    let reader = ImageReader::open(path).map_err(|_| ProgramError::InvalidInputPath)?;
-    reader
-        .decode()
-        .map_err(|_| ProgramError::FailedToDecodeInput)
+pub fn parse_color(s: &str) -> Result<Rgb<u8>, String> {
+    let named = match s.to_ascii_lowercase().as_str() {
+        "black" => Some([0, 0, 0]),
+        "white" => Some([255, 255, 255]),
+        "gray" | "grey" => Some([128, 128, 128]),
+        "red" => Some([255, 0, 0]),
+        "green" => Some([0, 255, 0]),
+        "blue" => Some([0, 0, 255]),
+        _ => None,
+    };
+    if let Some(rgb) = named {
+        return Ok(Rgb(rgb));
+    }
+
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    let digits: Vec<u8> = hex
+        .chars()
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<_>>()
+        .ok_or_else(|| format!("invalid color: {s}"))?;
+    match digits[..] {
+        [r, g, b] => Ok(Rgb([r * 17, g * 17, b * 17])),
+        [r1, r0, g1, g0, b1, b0] => Ok(Rgb([r1 * 16 + r0, g1 * 16 + g0, b1 * 16 + b0])),
+        _ => Err(format!("invalid color: {s}")),
+    }
 }
 
-fn get_image_format_from_response(response: &reqwest::blocking::Response) -> ImageFormatRes {
-    let headers = response.headers();
-    if let Some(content_type) = headers.get(CONTENT_TYPE) {
-        if let Ok(content_type) = content_type.to_str() {
-            if let Some(format) = image::ImageFormat::from_mime_type(content_type) {
-                ImageFormatRes::Some(format)
-            } else {
-                ImageFormatRes::Invalid
+// Histogram-matches the luminance to the reference image, when one is set
+fn match_tones(img: DynamicImage, options: &ConversionOptions) -> DynamicImage {
+    match options.tone_reference {
+        Some(reference) => reference.tone_map(&img, options.luma).apply(&img),
+        None => img,
+    }
+}
+
+// Tone maps the image first when asked, while high dynamic range values are intact.
+// Inputs with more than 8 bits per channel stay floating point, so their gradients
+// survive until the glyphs are picked
+fn composite_over_background(img: &DynamicImage, options: &ConversionOptions) -> DynamicImage {
+    let background = options.background;
+    let color = img.color();
+    if options.tone_mapping.is_none() && color.bytes_per_pixel() == color.channel_count() {
+        let mut rgba = img.to_rgba8();
+        for pixel in rgba.pixels_mut() {
+            let alpha = pixel[3] as f32 / u8::MAX as f32;
+            for c in 0..3 {
+                pixel[c] =
+                    (pixel[c] as f32 * alpha + background[c] as f32 * (1.0 - alpha)).round() as u8;
             }
+        }
+        return DynamicImage::ImageRgba8(rgba);
+    }
+    let mut rgba = match options.tone_mapping {
+        Some(mapping) => {
+            let white = options
+                .white_point
+                .unwrap_or_else(|| hdr::white_point(img, options.exposure, options.luma));
+            hdr::tone_map(img, mapping, options.exposure, options.luma, white)
+        }
+        None => img.to_rgba32f(),
+    };
+    for pixel in rgba.pixels_mut() {
+        let alpha = pixel[3];
+        for c in 0..3 {
+            pixel[c] = pixel[c] * alpha + background[c] as f32 / u8::MAX as f32 * (1.0 - alpha);
+        }
+    }
+    DynamicImage::ImageRgba32F(rgba)
+}
+
+// A 0..1 channel value as a byte
+fn quantize(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * u8::MAX as f32).round() as u8
+}
+
+fn read_input_bytes(params: &ProgramParameters) -> Result<Vec<u8>, ProgramError> {
+    let source = params.sources.resolve(params.input_path)?;
+    match source.open(params.input_path, params.fetcher)? {
+        SourceData::Encoded { bytes, .. } => Ok(bytes),
+        // Already decoded sources have no container to read frames from
+        SourceData::Decoded(_) => Err(source.decode_error()),
+    }
+}
+
+fn load_image(params: &ProgramParameters) -> Result<DynamicImage, ProgramError> {
+    let img = orientation::orient(
+        load_input(params, params.input_path)?,
+        params.rotation,
+        params.flip,
+    );
+    let img = crop::crop_input(img, params.crop, params.focus, &params.options);
+    Ok(watermark::blend(img, params.watermark.as_ref()))
+}
+
+fn load_input(params: &ProgramParameters, input: &str) -> Result<DynamicImage, ProgramError> {
+    let source = params.sources.resolve(input)?;
+    match source.open(input, params.fetcher)? {
+        SourceData::Encoded { bytes, format } => decode_encoded(
+            &bytes,
+            format,
+            Some(input),
+            &params.options,
+            params.isolate_decoding,
+            params.decode_limits,
+        )
+        .map_err(|err| match err {
+            ProgramError::FailedToDecodeInput => source.decode_error(),
+            err => err,
+        }),
+        SourceData::Decoded(img) => Ok(img),
+    }
+}
+
+// Decodes an encoded input, handing what image has no decoder for to the SVG
+// rasterizer and the RAW developer, in the worker process too when isolating. RAW
+// files are recognized by their extension, or without a name to go by, by rawloader
+// accepting what image couldn't decode
+#[cfg_attr(not(all(feature = "raw", feature = "svg")), allow(unused_variables))]
+fn decode_encoded(
+    bytes: &[u8],
+    format: Option<ImageFormat>,
+    input: Option<&str>,
+    options: &ConversionOptions,
+    isolate: bool,
+    limits: DecodeLimits,
+) -> Result<DynamicImage, ProgramError> {
+    #[cfg(feature = "raw")]
+    let develop = |bytes: &[u8]| {
+        if isolate {
+            isolation::develop_isolated(bytes, limits)
         } else {
-            ImageFormatRes::None
+            raw::develop(bytes, limits)
         }
-    } else {
-        ImageFormatRes::None
+    };
+    #[cfg(feature = "raw")]
+    if format.is_none() && input.is_some_and(raw::is_raw) {
+        return develop(bytes);
+    }
+    #[cfg(feature = "svg")]
+    if format.is_none() && svg::is_svg(bytes) {
+        return if isolate {
+            isolation::rasterize_isolated(bytes, options, limits)
+        } else {
+            svg::rasterize(bytes, options, limits)
+        };
+    }
+    let decoded = decode_bytes(bytes, format, isolate, limits);
+    #[cfg(feature = "raw")]
+    if format.is_none() && matches!(decoded, Err(ProgramError::FailedToDecodeInput)) {
+        return match develop(bytes) {
+            Err(ProgramError::FailedToDecodeInput) => decoded,
+            developed => developed,
+        };
+    }
+    decoded
+}
+
+fn decode_bytes(
+    bytes: &[u8],
+    format: Option<ImageFormat>,
+    isolate: bool,
+    limits: DecodeLimits,
+) -> Result<DynamicImage, ProgramError> {
+    // Not even the header is parsed in this process when isolating, the worker
+    // checks the limits itself
+    if isolate {
+        return decode_isolated(bytes, format, limits);
+    }
+
+    // Only the header is parsed here, so oversized images are rejected before
+    // anything is allocated for their pixels
+    let (w, h) = image_reader(bytes, format)?
+        .into_dimensions()
+        .map_err(|_| ProgramError::FailedToDecodeInput)?;
+    limits.check_dimensions(w, h)?;
+
+    let mut reader = image_reader(bytes, format)?;
+    reader.limits(limits.to_image_limits());
+    reader.decode().map_err(|err| match err {
+        ImageError::Limits(_) => ProgramError::ImageTooLarge,
+        _ => ProgramError::FailedToDecodeInput,
+    })
+}
+
+fn image_reader(
+    bytes: &[u8],
+    format: Option<ImageFormat>,
+) -> Result<ImageReader<Cursor<&[u8]>>, ProgramError> {
+    match format {
+        Some(format) => Ok(ImageReader::with_format(Cursor::new(bytes), format)),
+        None => ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .map_err(|_| ProgramError::FailedToDecodeInput),
     }
 }
@@ -0,0 +1,40 @@
+use serde::Deserialize;
+
+use crate::ProgramError;
+
+// Regions are given in cells of the generated art, not source image pixels,
+// so a map stays valid when only the source resolution changes
+#[derive(Debug, Clone, Deserialize)]
+pub struct LinkRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub url: String,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+impl LinkRegion {
+    fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x && y >= self.y && x - self.x < self.width && y - self.y < self.height
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct LinkMap {
+    pub regions: Vec<LinkRegion>,
+}
+
+impl LinkMap {
+    // Earlier regions win where regions overlap
+    pub fn region_at(&self, x: u32, y: u32) -> Option<&LinkRegion> {
+        self.regions.iter().find(|region| region.contains(x, y))
+    }
+}
+
+pub fn read_link_map(path: &str) -> Result<LinkMap, ProgramError> {
+    let data = std::fs::read_to_string(path).map_err(|_| ProgramError::FailedToReadLinkMap)?;
+    serde_json::from_str(&data).map_err(|_| ProgramError::InvalidLinkMap)
+}
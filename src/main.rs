@@ -1,58 +1,697 @@
 
-use image2unicodeart::{generate_image, ProgramError, ProgramParameters};
-use clap::Parser;
+use image2unicodeart::{calibrate_charset_file, capabilities_json, charset_preset, generate_image, load_palette, load_tone_reference, load_watermark, parse_color, parse_filter, read_charset_file, read_link_map, read_session, run_decode_worker, run_visualizer, sort_charset, DECODE_WORKER_ARG, Align, Animation, Border, Caption, CaptionPosition, CellMetrics, CellMode, ColorMode, DecodeLimits, Dither, ConversionOptions, Crop, Fetcher, Flip, Focus, Generator, GlyphAtlas, Luma, Montage, OutputFormat, ProgramError, ProgramParameters, ProgressiveBudget, Rect, Rotation, Slideshow, SourceRegistry, Threshold, ToneMapping, VisualizerMode, Watermark, CHARSET_PRESETS};
+use clap::{CommandFactory, FromArgMatches, Parser};
+use i18n::Messages;
+use image::{imageops::FilterType, Rgb};
+use std::time::Duration;
+
+#[cfg(feature = "http")]
+use image2unicodeart::{HttpCache, ReqwestFetcher, RetryPolicy};
+#[cfg(not(feature = "http"))]
+use image2unicodeart::NoNetwork;
+
+mod i18n;
 
 #[derive(Parser, Debug)]
 #[command(about = "Tool for converting images to Unicode art.")]
 struct Args {
     #[clap(index = 1)]
-    #[arg(help="Input file path or URL")]
-    input: String,
+    #[arg(required_unless_present_any=["capabilities", "visualizer", "generate_anim", "montage", "compare"], help="Input file path, URL, data: URI, - for stdin, generate:<plasma|life|fire>, or a directory with --slideshow or --contact-sheet")]
+    input: Option<String>,
+
+    #[arg(long, help="Print a JSON description of this build's capabilities and exit")]
+    capabilities: bool,
 
-    #[arg(short, long, help="Output file path")]
+    #[arg(short, long, help="Output file path (written atomically), - for stdout, clipboard: or tcp://host:port")]
     output: Option<String>,
 
+    #[arg(long, requires="output", help="Append to the output file instead of replacing it")]
+    append: bool,
+
+    #[arg(long, value_parser=parse_separator, help="Written after every frame or image of output (escapes like \\f, \\n allowed)")]
+    frame_separator: Option<String>,
+
+    #[arg(long, default_value="text", help="Output format (text, html, svg, png, ans, markdown, layers, discord-webhook, slack-webhook)")]
+    format: OutputFormat,
+
+    #[arg(long, help="Render a --format layers bundle (e.g. with edited colors) instead of converting an image")]
+    from_layers: bool,
+
+    #[arg(long, help="Print glyph usage counts, output sizes per format and the brightness range used to stderr")]
+    stats: bool,
+
+    #[arg(long, value_name="LAYERS", help="Keep the glyphs of a --format layers bundle and take the colors from the input image")]
+    recolor: Option<String>,
+
+    #[arg(long, required_if_eq_any=[("format", "discord-webhook"), ("format", "slack-webhook")], help="Discord or Slack webhook the art is posted to (with --format discord-webhook/slack-webhook)")]
+    webhook_url: Option<String>,
+
+    #[arg(long, help="Terminal colors for text output (none, 16, 256, truecolor); truecolor when a palette is given, none otherwise")]
+    color: Option<ColorMode>,
+
+    #[arg(long, help="Snap colors to a palette preset (gruvbox, solarized, nord, dracula, gameboy) or a file of #rrggbb colors")]
+    palette: Option<String>,
+
+    #[arg(long, help="Monospace TTF/OTF font used to rasterize PNG output")]
+    font: Option<String>,
+
+    #[arg(long, help="JSON list of {x, y, width, height, url, title} cell regions to make clickable in HTML/SVG output")]
+    link_map: Option<String>,
+
+    #[arg(long, default_value_t = 14.0, help="Font size for SVG output")]
+    font_size: f32,
+
+    #[arg(long, help="Cell advance for SVG output (default 0.6 x font size)")]
+    cell_advance: Option<f32>,
+
+    #[arg(long, help="Line height for SVG output (default 1.2 x font size)")]
+    line_height: Option<f32>,
+
+    #[arg(long, default_value_t = 100_000_000, help="Refuse to decode images with more pixels than this (0 for no limit)")]
+    max_pixels: u64,
+
+    #[arg(long, default_value_t = 1024, help="Memory the image decoder may allocate, in MiB (0 for no limit)")]
+    max_decode_memory: u64,
+
+    #[arg(long, default_value="30s", value_parser=parse_duration, help="Give up on URL inputs that take longer than this (0 for no limit)")]
+    timeout: Duration,
+
+    #[arg(long, default_value_t = 64, help="Largest URL download accepted, in MiB (0 for no limit)")]
+    max_download_size: u64,
+
+    #[arg(long, default_value_t = 0, help="Retry failed URL downloads this many times")]
+    retries: u32,
+
+    #[arg(long, default_value="500ms", value_parser=parse_duration, help="Delay before the first retry, doubled (with jitter) for every further one")]
+    retry_backoff: Duration,
+
+    #[arg(long, value_delimiter=',', default_value="5xx,timeout", value_parser=["5xx", "timeout"], help="Failures worth retrying besides connection errors (5xx, timeout)")]
+    retry_on: Vec<String>,
+
+    #[arg(long, help="Proxy for URL inputs (http://, https:// or socks5://), overriding HTTP_PROXY/HTTPS_PROXY")]
+    proxy: Option<String>,
+
+    #[arg(long, value_parser=parse_header, help="Extra request header for URL inputs, e.g. 'Authorization: Bearer <token>' (repeatable)")]
+    header: Vec<(String, String)>,
+
+    #[arg(long, help="Basic auth credentials for URL inputs as user[:password]")]
+    user: Option<String>,
+
+    #[arg(long, help="User-Agent sent when downloading URL inputs")]
+    user_agent: Option<String>,
+
+    #[arg(long, help="Directory caching downloaded URL inputs (default: the user cache directory)")]
+    cache_dir: Option<String>,
+
+    #[arg(long, help="Always download URL inputs, bypassing the cache")]
+    no_cache: bool,
+
+    #[arg(long, default_value_t = 256, help="Size of the download cache, in MiB, beyond which the least recently used images are evicted")]
+    max_cache_size: u64,
+
+    #[arg(long, help="Convert in horizontal strips, writing plain text rows as they're produced (for huge images)")]
+    stream: bool,
+
+    #[arg(long, conflicts_with_all=["output", "stream", "animate", "marquee"], help="Write a coarse preview at once, then refine it in place on the terminal (for slow links)")]
+    progressive: bool,
+
+    #[arg(long, value_parser=parse_duration, requires="progressive", help="Stop refining --progressive output after this long (e.g. 2s, 500ms)")]
+    time_budget: Option<Duration>,
+
+    #[arg(long, value_name="BYTES", requires="progressive", help="Stop refining --progressive output once this many bytes are written")]
+    byte_budget: Option<usize>,
+
+    #[arg(long, help="Decode the input in a separate process (for untrusted inputs)")]
+    isolate_decoding: bool,
+
     #[arg(short, long, help="Output width (number of symbols)")]
     width: Option<u32>,
 
-    #[arg(short, long, default_value_t = 0.5, help="Width/height of symbols")]
-    symbol_aspect_ratio: f32,
+    #[arg(long, help="Never exceed this many columns, e.g. to fit a Markdown code block")]
+    max_columns: Option<u32>,
+
+    #[arg(long, value_name="ROWS", help="Fit the art into this many rows, narrowing it if needed")]
+    height: Option<u32>,
+
+    #[arg(long, num_args=0..=1, default_missing_value=" ", value_name="FILL", conflicts_with="progressive", help="Pad the art with FILL (default space) to exactly --width by --height, or the terminal's size")]
+    letterbox: Option<char>,
+
+    #[arg(long, default_value="none", conflicts_with="progressive", help="Frame the art with a border (ascii, rounded, double, none)")]
+    border: Border,
+
+    #[arg(long, help="Title set into the top edge of the --border")]
+    title: Option<String>,
+
+    #[arg(long, conflicts_with="progressive", help="Text written over the art, one row per line")]
+    caption: Option<String>,
+
+    #[arg(long, default_value="bottom-center", requires="caption", help="Where the --caption sits (top-left, top-center, top-right, center-left, center, center-right, bottom-left, bottom-center, bottom-right)")]
+    caption_pos: CaptionPosition,
+
+    #[arg(long, value_parser=parse_color, requires="caption", help="Color of the --caption (name or #rrggbb), by default whichever stands out against the background")]
+    caption_color: Option<Rgb<u8>>,
+
+    #[arg(long, default_value="left", conflicts_with="progressive", help="Position the art within the terminal's width (left, center, right)")]
+    align: Align,
+
+    #[arg(short, long, help="Width/height of symbols [default: 0.5]")]
+    symbol_aspect_ratio: Option<f32>,
+
+    #[arg(long, default_value="catmullrom", value_parser=parse_filter, help="Resize filter (nearest, triangle, catmullrom, gaussian, lanczos3)")]
+    filter: FilterType,
+
+    #[arg(long, default_value="glyphs", help="Cell rendering mode (glyphs, emoji, halfblock, braille, octant: Unicode 16 blocks, adaptive: braille only where there is detail)")]
+    mode: CellMode,
+
+    #[arg(long, value_parser=parse_snap, help="Round the sampled grid to multiples of 2, 4 or 8 pixels (e.g. to fill whole braille cells and dither tiles)")]
+    snap: Option<u32>,
+
+    #[arg(long, value_parser=parse_cell_size, value_name="WxH", conflicts_with_all=["width", "oneline_height"], help="Source pixels each cell samples, e.g. 8x16, instead of deriving them from --width and --symbol-aspect-ratio")]
+    cell_size: Option<(u32, u32)>,
+
+    #[arg(long, value_parser=clap::value_parser!(u32).range(1..=3), help="Render a 1-3 row strip of truecolor background cells for tmux status bars and prompts")]
+    oneline_height: Option<u32>,
+
+    #[arg(short, long, help="Charset ramp, or weighted glyphs like \".:0.05,+:0.3,#:0.8\" [default: \" ░▒▓█\"]")]
+    charset: Option<String>,
+
+    #[arg(long, conflicts_with="charset", value_parser=parse_preset, help="Named charset preset (blocks, ascii, ascii-extended, dots, binary)")]
+    preset: Option<&'static str>,
+
+    #[arg(long, conflicts_with_all=["charset", "preset"], help="Read the charset from a UTF-8 file (single line or one glyph per line)")]
+    charset_file: Option<String>,
+
+    #[arg(long, help="Order and weight the charset by ink coverage measured with this TTF/OTF font")]
+    calibrate_font: Option<String>,
+
+    #[arg(long, conflicts_with="calibrate_font", help="Reorder the charset from the least to the most ink (measured with --font if given, estimated otherwise)")]
+    sort_charset: bool,
+
+    #[arg(long, help="Pick glyphs by shape, matching each cell against glyphs rasterized with this TTF/OTF font")]
+    match_font: Option<String>,
+
+    #[arg(long, num_args=0..=1, default_missing_value="ordered", help="Dither brightness across charset levels (none, ordered, temporal for animations) [default: none]")]
+    dither: Option<Dither>,
+
+    #[arg(long, help="Scale brightness contrast around mid-gray before picking glyphs (1 for unchanged) [default: 1]")]
+    contrast: Option<f32>,
+
+    #[arg(long, help="Gamma applied to brightness before picking glyphs; above 1 brightens the midtones [default: 1]")]
+    gamma: Option<f32>,
+
+    #[arg(long, value_name="CUTOFF", help="1-bit mode: only the charset's first and last glyphs (blank or lit sub-cells), split at this brightness (0..1), or auto to pick it with Otsu's method")]
+    threshold: Option<Threshold>,
+
+    #[arg(long, help="Tone map high dynamic range or very contrasty inputs (reinhard, sigmoid) so they don't collapse into the extreme glyphs")]
+    tone_map: Option<ToneMapping>,
+
+    #[arg(long, default_value_t=0.0, allow_negative_numbers=true, requires="tone_map", help="Exposure in stops applied before --tone-map")]
+    exposure: f32,
+
+    #[arg(long, default_value="rec709", help="Luma formula (rec709, rec601)")]
+    luma: Luma,
+
+    #[arg(long, help="Histogram-match the input's luminance to this reference image for consistent tonality across a batch")]
+    match_tones: Option<String>,
+
+    #[arg(long, default_value="black", value_parser=parse_color, help="Background color transparent pixels are composited over (name or #rrggbb)")]
+    background: Rgb<u8>,
+
+    #[arg(long, help="Render cells with alpha below this threshold (0..1) as blank")]
+    alpha_threshold: Option<f32>,
+
+    #[arg(long, default_value_t = ' ', help="Character used for transparent cells")]
+    transparent_char: char,
+
+    #[arg(long, help="Leave transparent cells untouched (cursor moves in text, empty boxes in HTML) for overlaying")]
+    overlay: bool,
+
+    #[arg(long, help="Animate a still image (reveal, typewriter, dissolve, kenburns)")]
+    animate: Option<Animation>,
+
+    #[arg(long, default_value="3s", value_parser=parse_duration, help="Animation duration (e.g. 3s, 500ms)")]
+    duration: Duration,
 
-    #[arg(short, long, default_value_t=String::from(" ░▒▓█"))]
-    charset: String,
+    #[arg(long, value_name="DEGREES", help="Rotate the input clockwise by 90, 180 or 270 degrees")]
+    rotate: Option<Rotation>,
+
+    #[arg(long, help="Mirror the input horizontally (h) or vertically (v), after any rotation")]
+    flip: Option<Flip>,
+
+    #[arg(long, value_name="X,Y,W,H", help="Convert only this region of the input, in pixels or percentages (e.g. 10%,0,50%,100%)")]
+    crop: Option<Crop>,
+
+    #[arg(long, help="Cover crop the input to fill the output width and terminal height, keeping the center, top, bottom, left, right, top-left, top-right, bottom-left or bottom-right")]
+    focus: Option<Focus>,
+
+    #[arg(long, value_name="PATH", help="Blend this image (e.g. a logo) into the input before converting it")]
+    watermark: Option<String>,
+
+    #[arg(long, default_value_t=0.5, requires="watermark", help="Opacity of the --watermark, 0 to 1")]
+    watermark_opacity: f32,
+
+    #[arg(long, default_value="bottom-right", requires="watermark", help="Where the --watermark sits (center, top, bottom, left, right, top-left, top-right, bottom-left, bottom-right)")]
+    watermark_pos: Focus,
+
+    #[arg(long, help="Ken Burns start rectangle in pixels (x,y,w,h)")]
+    from_rect: Option<Rect>,
+
+    #[arg(long, help="Ken Burns end rectangle in pixels (x,y,w,h)")]
+    to_rect: Option<Rect>,
+
+    #[arg(long, help="Scroll a wide image horizontally through a terminal-wide viewport")]
+    marquee: bool,
+
+    #[arg(long, default_value_t = 10, help="Marquee speed (columns per second)")]
+    speed: u32,
+
+    #[arg(long, help="Explore the image full screen, panning with the arrow keys and zooming with +/- (q quits)")]
+    view: bool,
+
+    #[arg(long, help="Adjust charset, aspect ratio, contrast, gamma, dithering and colors interactively, then print the flags")]
+    tune: bool,
+
+    #[arg(long, value_name="FILE", help="Save the --view or --tune adjustments as a session script")]
+    record_session: Option<String>,
+
+    #[arg(long, value_name="FILE", help="Apply the final settings of a recorded session script; flags given explicitly still win")]
+    apply_session: Option<String>,
+
+    #[arg(long, conflicts_with="output", help="Show every image in the input directory fitted to the terminal, advancing after --delay or on a key press")]
+    slideshow: bool,
+
+    #[arg(long, default_value="3s", value_parser=parse_duration, help="Time each slideshow image is shown (e.g. 3s, 500ms)")]
+    delay: Duration,
+
+    #[arg(long, requires="slideshow", help="Show the slideshow images in random order")]
+    shuffle: bool,
+
+    #[arg(long, num_args=1.., value_name="INPUTS", help="Convert several inputs and lay them out as tiles of one image")]
+    montage: Vec<String>,
+
+    #[arg(long, num_args=2, value_names=["BEFORE", "AFTER"], conflicts_with="montage", help="Show two images at the same size side by side (stacked on narrow terminals)")]
+    compare: Vec<String>,
+
+    #[arg(long, requires="montage", help="Tiles per row of a --montage (default: all in one row)")]
+    montage_columns: Option<u32>,
+
+    #[arg(long, requires="montage", help="Leave the file names out from under --montage tiles")]
+    no_labels: bool,
+
+    #[arg(long, conflicts_with_all=["slideshow", "montage", "compare"], help="Lay out labeled thumbnails of every image in the input directory, as many per row as fit --width")]
+    contact_sheet: bool,
+
+    #[arg(long, default_value_t=16, value_name="CELLS", help="Width of each --contact-sheet thumbnail")]
+    thumbnail_width: u32,
+
+    #[arg(long, value_name="PATH", help="Write the --montage or --contact-sheet inputs that failed to convert to PATH, one per line, for retrying")]
+    failures_file: Option<String>,
+
+    #[arg(long, help="Play an animated GIF input")]
+    play: bool,
+
+    #[arg(long, help="Playback frame rate for animated inputs")]
+    fps: Option<u32>,
+
+    #[arg(long, help="Blend adjacent frames when playing back at a higher frame rate")]
+    interpolate: bool,
+
+    #[arg(long, help="Stretch brightness levels to each scene of --play/--generate-anim animations, so dark scenes keep their detail")]
+    adaptive_levels: bool,
+
+    #[arg(long, help="Benchmark a few settings before --play/--generate-anim and use the best one that keeps up with the frame rate")]
+    auto_tune: bool,
+
+    #[arg(long, num_args=0..=1, default_missing_value="spectrum", help="Visualize microphone audio (spectrum, waveform)")]
+    visualizer: Option<VisualizerMode>,
+
+    #[arg(long, help="Play a procedurally generated animation (plasma, life, fire) for --duration")]
+    generate_anim: Option<Generator>,
+}
+
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (value, scale) = if let Some(ms) = s.strip_suffix("ms") {
+        (ms, 0.001)
+    } else {
+        (s.strip_suffix('s').unwrap_or(s), 1.0)
+    };
+    let value: f32 = value.parse().map_err(|_| format!("invalid duration: {s}"))?;
+    Duration::try_from_secs_f32(value * scale).map_err(|_| format!("invalid duration: {s}"))
+}
+
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    s.split_once(':')
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .ok_or_else(|| format!("invalid header: {s} (expected \"Name: value\")"))
+}
+
+fn parse_preset(s: &str) -> Result<&'static str, String> {
+    charset_preset(s).ok_or_else(|| {
+        let names: Vec<&str> = CHARSET_PRESETS.iter().map(|(name, _)| *name).collect();
+        format!("unknown preset: {s} (available: {})", names.join(", "))
+    })
+}
+
+fn parse_separator(s: &str) -> Result<String, String> {
+    let mut separator = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            separator.push(c);
+            continue;
+        }
+        separator.push(match chars.next() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('f') => '\x0c',
+            Some('0') => '\0',
+            Some('e') => '\x1b',
+            Some('\\') => '\\',
+            _ => return Err(format!("invalid escape in separator: {s}")),
+        });
+    }
+    Ok(separator)
+}
+
+fn parse_snap(s: &str) -> Result<u32, String> {
+    match s.parse() {
+        Ok(multiple @ (2 | 4 | 8)) => Ok(multiple),
+        _ => Err(format!("invalid snap: {s} (expected 2, 4 or 8)")),
+    }
+}
+
+fn parse_cell_size(s: &str) -> Result<(u32, u32), String> {
+    s.split_once('x')
+        .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+        .filter(|&(w, h)| w > 0 && h > 0)
+        .ok_or_else(|| format!("invalid cell size: {s} (expected WxH in pixels, e.g. 8x16)"))
 }
 
 fn main() {
-    let args = Args::parse();
+    let mut raw_args = std::env::args().skip(1);
+    if raw_args.next().as_deref() == Some(DECODE_WORKER_ARG) {
+        std::process::exit(run_decode_worker(raw_args));
+    }
+
+    let messages = Messages::load();
+    let matches = messages.localize_command(Args::command()).get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    if args.capabilities {
+        println!("{}", capabilities_json());
+        return;
+    }
+
+    if let Err(err) = run(&args) {
+        report_error(err, &args, &messages);
+    }
+}
 
+fn run(args: &Args) -> Result<(), ProgramError> {
     let output_path_opt = args.output.as_ref().map(|x| x.as_ref());
 
-    let res = generate_image(&ProgramParameters {
-        input_path: &args.input,
-        output_path: output_path_opt,
-        output_width: args.width,
-        symbol_aspect_ratio: args.symbol_aspect_ratio,
-        charset: &args.charset,
+    // Settings resolve as: explicit flag, then the applied session, then the default
+    let defaults = ConversionOptions::default();
+    let session = args.apply_session.as_deref().map(read_session).transpose()?.unwrap_or_default();
+    let charset_from_file = args.charset_file.as_deref().map(read_charset_file).transpose()?;
+    let charset = charset_from_file
+        .as_deref()
+        .or(args.preset)
+        .or(args.charset.as_deref())
+        .or(session.charset.as_deref())
+        .unwrap_or(defaults.charset);
+    let symbol_aspect_ratio = args.symbol_aspect_ratio.or(session.symbol_aspect_ratio).unwrap_or(defaults.symbol_aspect_ratio);
+    let font_data = args
+        .font
+        .as_deref()
+        .map(|font_path| std::fs::read(font_path).map_err(|_| ProgramError::InvalidFont))
+        .transpose()?;
+    let sorted = args.sort_charset.then(|| sort_charset(charset, font_data.as_deref()));
+    let charset = sorted.as_deref().unwrap_or(charset);
+    let calibrated = args
+        .calibrate_font
+        .as_deref()
+        .map(|font_path| calibrate_charset_file(charset, font_path))
+        .transpose()?;
+    let charset = calibrated.as_deref().unwrap_or(charset);
+    let glyph_atlas = args
+        .match_font
+        .as_deref()
+        .map(|font_path| {
+            let font_data = std::fs::read(font_path).map_err(|_| ProgramError::InvalidFont)?;
+            GlyphAtlas::new(charset, &font_data, symbol_aspect_ratio)
+        })
+        .transpose()?;
+    let link_map = args.link_map.as_deref().map(read_link_map).transpose()?;
+    let palette = args.palette.as_deref().map(load_palette).transpose()?;
+    let tone_reference = args
+        .match_tones
+        .as_deref()
+        .map(|path| load_tone_reference(path, args.luma))
+        .transpose()?;
+    let watermark_image = args.watermark.as_deref().map(load_watermark).transpose()?;
+    let color = args.color.or(session.color).unwrap_or(if palette.is_some() || args.oneline_height.is_some() {
+        ColorMode::TrueColor
+    } else {
+        ColorMode::None
     });
 
-    match res {
-        Ok(_) => {}
-        Err(err) => match err {
-            ProgramError::InvalidInputPath => {
-                println!("Failed to open: {}", args.input);
-            }
-            ProgramError::FailedToDecodeInput => {
-                println!("Failed to decode input image!");
-            }
-            ProgramError::FailedToWriteToOutput => {
-                println!("Failed to save output to: {}", args.output.unwrap());
-            }
-            ProgramError::FailedToDownload => {
-                println!("Failed to download: {}", args.input);
-            },
-            ProgramError::DownloadInvalid => {
-                println!("Invalid source: {}", args.input);
-            },
+    let options = ConversionOptions {
+        output_width: args.width,
+        symbol_aspect_ratio,
+        filter: args.filter,
+        charset,
+        luma: args.luma,
+        background: args.background,
+        alpha_threshold: args.alpha_threshold,
+        transparent_char: args.transparent_char,
+        glyph_atlas: glyph_atlas.as_ref(),
+        format: args.format,
+        cell_metrics: cell_metrics(args),
+        font: font_data.as_deref(),
+        link_map: link_map.as_ref(),
+        overlay: args.overlay,
+        max_columns: args.max_columns,
+        max_rows: args.height,
+        color,
+        palette: palette.as_ref(),
+        dither: args.dither.or(session.dither).unwrap_or(defaults.dither),
+        mode: args.mode,
+        snap: args.snap,
+        cell_size: args.cell_size,
+        oneline_height: args.oneline_height,
+        tone_reference: tone_reference.as_ref(),
+        frame: 0,
+        brightness_range: None,
+        contrast: args.contrast.or(session.contrast).unwrap_or(defaults.contrast),
+        gamma: args.gamma.or(session.gamma).unwrap_or(defaults.gamma),
+        threshold: args.threshold,
+        tone_mapping: args.tone_map,
+        exposure: args.exposure,
+        white_point: None,
+    };
+
+    if let Some(mode) = args.visualizer {
+        return run_visualizer(mode, &options);
+    }
+
+    let fetcher = fetcher(args);
+    let sources = SourceRegistry::with_defaults();
+    generate_image(&ProgramParameters {
+        input_path: args.input.as_deref().unwrap_or_default(),
+        output_path: output_path_opt,
+        isolate_decoding: args.isolate_decoding,
+        decode_limits: DecodeLimits {
+            max_pixels: Some(args.max_pixels).filter(|&max| max > 0),
+            max_memory: Some(args.max_decode_memory.saturating_mul(1024 * 1024)).filter(|&max| max > 0),
         },
+        fetcher: fetcher.as_ref(),
+        sources: &sources,
+        options,
+        animation: args.animate,
+        animation_duration: args.duration,
+        rotation: args.rotate,
+        flip: args.flip,
+        crop: args.crop.or(session.crop()),
+        focus: args.focus,
+        watermark: watermark_image.as_ref().map(|image| Watermark {
+            image,
+            opacity: args.watermark_opacity,
+            position: args.watermark_pos,
+        }),
+        from_rect: args.from_rect,
+        to_rect: args.to_rect,
+        marquee_speed: args.marquee.then_some(args.speed),
+        slideshow: args.slideshow.then_some(Slideshow {
+            delay: args.delay,
+            shuffle: args.shuffle,
+        }),
+        montage: (!args.montage.is_empty()).then_some(Montage {
+            inputs: &args.montage,
+            columns: args.montage_columns,
+            labels: !args.no_labels,
+        }),
+        contact_sheet: args.contact_sheet.then_some(args.thumbnail_width),
+        failures_file: args.failures_file.as_deref(),
+        letterbox: args.letterbox,
+        border: args.border,
+        align: args.align,
+        title: args.title.as_deref(),
+        caption: args.caption.as_deref().map(|text| Caption {
+            text,
+            position: args.caption_pos,
+            color: args.caption_color,
+        }),
+        compare: match &args.compare[..] {
+            [before, after] => Some([before.as_str(), after.as_str()]),
+            _ => None,
+        },
+        stats: args.stats,
+        recolor: args.recolor.as_deref(),
+        from_layers: args.from_layers,
+        view: args.view,
+        tune: args.tune,
+        record_session: args.record_session.as_deref(),
+        play: args.play,
+        playback_fps: args.fps,
+        interpolate: args.interpolate,
+        adaptive_levels: args.adaptive_levels,
+        auto_tune: args.auto_tune,
+        generator: args.generate_anim,
+        stream: args.stream,
+        progressive: args.progressive.then_some(ProgressiveBudget {
+            time: args.time_budget,
+            bytes: args.byte_budget,
+        }),
+        append: args.append,
+        frame_separator: args.frame_separator.as_deref(),
+        webhook_url: args.webhook_url.as_deref(),
+    })
+}
+
+#[cfg(feature = "http")]
+fn fetcher(args: &Args) -> Box<dyn Fetcher> {
+    Box::new(ReqwestFetcher {
+        timeout: Some(args.timeout).filter(|timeout| !timeout.is_zero()),
+        max_download_size: Some(args.max_download_size.saturating_mul(1024 * 1024)).filter(|&max| max > 0),
+        retry: RetryPolicy {
+            retries: args.retries,
+            backoff: args.retry_backoff,
+            on_server_error: args.retry_on.iter().any(|condition| condition == "5xx"),
+            on_timeout: args.retry_on.iter().any(|condition| condition == "timeout"),
+        },
+        proxy: args.proxy.clone(),
+        headers: args.header.clone(),
+        basic_auth: args.user.as_deref().map(|user| match user.split_once(':') {
+            Some((user, password)) => (user.to_string(), Some(password.to_string())),
+            None => (user.to_string(), None),
+        }),
+        user_agent: args.user_agent.clone(),
+        cache: args
+            .cache_dir
+            .as_deref()
+            .map(std::path::PathBuf::from)
+            .or_else(HttpCache::default_dir)
+            .filter(|_| !args.no_cache)
+            .map(|dir| HttpCache::new(dir, args.max_cache_size.saturating_mul(1024 * 1024))),
+    })
+}
+
+#[cfg(not(feature = "http"))]
+fn fetcher(_args: &Args) -> Box<dyn Fetcher> {
+    Box::new(NoNetwork)
+}
+
+fn cell_metrics(args: &Args) -> CellMetrics {
+    let defaults = CellMetrics::from_font_size(args.font_size);
+    CellMetrics {
+        advance: args.cell_advance.unwrap_or(defaults.advance),
+        line_height: args.line_height.unwrap_or(defaults.line_height),
+        ..defaults
     }
 }
+
+fn report_error(err: ProgramError, args: &Args, messages: &Messages) {
+    let input = args.input.as_deref().unwrap_or_default();
+    let (id, message_args): (&str, Vec<(&str, &str)>) = match err {
+        ProgramError::InvalidInputPath => ("error-invalid-input-path", vec![("input", input)]),
+        ProgramError::FailedToDecodeInput => ("error-failed-to-decode-input", vec![]),
+        ProgramError::FailedToWriteToOutput => (
+            "error-failed-to-write-to-output",
+            vec![("output", args.output.as_deref().unwrap_or_default())],
+        ),
+        ProgramError::FailedToDownload => ("error-failed-to-download", vec![("input", input)]),
+        ProgramError::HostUnreachable => ("error-host-unreachable", vec![("input", input)]),
+        ProgramError::DownloadRejected => ("error-download-rejected", vec![("input", input)]),
+        ProgramError::DownloadInvalid => ("error-download-invalid", vec![("input", input)]),
+        ProgramError::DownloadTimedOut => ("error-download-timed-out", vec![("input", input)]),
+        ProgramError::DownloadTooLarge => ("error-download-too-large", vec![("input", input)]),
+        ProgramError::InvalidProxy => (
+            "error-invalid-proxy",
+            vec![("proxy", args.proxy.as_deref().unwrap_or_default())],
+        ),
+        ProgramError::InvalidHeader => ("error-invalid-header", vec![]),
+        ProgramError::InvalidDataUri => ("error-invalid-data-uri", vec![]),
+        ProgramError::NetworkDisabled => ("error-network-disabled", vec![("input", input)]),
+        ProgramError::WebhookFailed => (
+            "error-webhook-failed",
+            vec![("url", args.webhook_url.as_deref().unwrap_or_default())],
+        ),
+        ProgramError::FailedToReadCharset => (
+            "error-failed-to-read-charset",
+            vec![("path", args.charset_file.as_deref().unwrap_or_default())],
+        ),
+        ProgramError::FailedToReadLinkMap => (
+            "error-failed-to-read-link-map",
+            vec![("path", args.link_map.as_deref().unwrap_or_default())],
+        ),
+        ProgramError::InvalidLinkMap => (
+            "error-invalid-link-map",
+            vec![("path", args.link_map.as_deref().unwrap_or_default())],
+        ),
+        ProgramError::FailedToReadWatermark => (
+            "error-failed-to-read-watermark",
+            vec![("path", args.watermark.as_deref().unwrap_or_default())],
+        ),
+        ProgramError::FailedToReadToneReference => (
+            "error-failed-to-read-tone-reference",
+            vec![("path", args.match_tones.as_deref().unwrap_or_default())],
+        ),
+        ProgramError::FailedToReadPalette => (
+            "error-failed-to-read-palette",
+            vec![("path", args.palette.as_deref().unwrap_or_default())],
+        ),
+        ProgramError::InvalidPalette => (
+            "error-invalid-palette",
+            vec![("path", args.palette.as_deref().unwrap_or_default())],
+        ),
+        ProgramError::InvalidLayers => (
+            "error-invalid-layers",
+            vec![("input", args.recolor.as_deref().unwrap_or(input))],
+        ),
+        ProgramError::InvalidSession => (
+            "error-invalid-session",
+            vec![("path", args.apply_session.as_deref().unwrap_or_default())],
+        ),
+        ProgramError::EmptyInput => ("error-empty-input", vec![("input", input)]),
+        ProgramError::InvalidOutputWidth => ("error-invalid-output-width", vec![]),
+        ProgramError::OutputTooLarge => ("error-output-too-large", vec![]),
+        ProgramError::EmptyRect => ("error-empty-rect", vec![]),
+        ProgramError::InvalidSymbolAspectRatio => ("error-invalid-symbol-aspect-ratio", vec![]),
+        ProgramError::InvalidToneAdjustment => ("error-invalid-tone-adjustment", vec![]),
+        ProgramError::EmptyCharset => ("error-empty-charset", vec![]),
+        ProgramError::ImageTooLarge => ("error-image-too-large", vec![("input", input)]),
+        ProgramError::DecoderCrashed => ("error-decoder-crashed", vec![("input", input)]),
+        ProgramError::DecoderTimedOut => ("error-decoder-timed-out", vec![("input", input)]),
+        ProgramError::InvalidFont => (
+            "error-invalid-font",
+            vec![("path", args.calibrate_font.as_deref().or(args.match_font.as_deref()).or(args.font.as_deref()).unwrap_or_default())],
+        ),
+        ProgramError::MissingFont => ("error-missing-font", vec![]),
+        ProgramError::AudioCaptureFailed => ("error-audio-capture-failed", vec![]),
+        ProgramError::VisualizerUnavailable => ("error-visualizer-unavailable", vec![]),
+        ProgramError::ViewerUnavailable => ("error-viewer-unavailable", vec![]),
+    };
+    println!("{}", messages.format(id, &message_args).unwrap_or_else(|| id.to_string()));
+}
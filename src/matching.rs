@@ -0,0 +1,142 @@
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use image::{DynamicImage, Rgb};
+
+use crate::{quantize, AsciiImage, Charset, ConversionOptions, ProgramError};
+
+const ATLAS_CELL_WIDTH: u32 = 8;
+
+// Rasterized glyph bitmaps used to pick, for every cell, the glyph whose shape
+// best matches the underlying pixel block rather than just its mean brightness
+#[derive(Debug, Clone)]
+pub struct GlyphAtlas {
+    cell_size: (u32, u32),
+    glyphs: Vec<String>,
+    bitmaps: Vec<Vec<f32>>,
+}
+
+impl GlyphAtlas {
+    pub fn new(
+        charset: &str,
+        font_data: &[u8],
+        symbol_aspect_ratio: f32,
+    ) -> Result<Self, ProgramError> {
+        let font = FontRef::try_from_slice(font_data).map_err(|_| ProgramError::InvalidFont)?;
+        let cell_w = ATLAS_CELL_WIDTH;
+        let cell_h = (cell_w as f32 / symbol_aspect_ratio)
+            .round()
+            .clamp(1.0, 64.0) as u32;
+
+        // Stretch the font so one advance and one line exactly fill a cell
+        let unit = font.as_scaled(PxScale::from(1.0));
+        let advance = unit.h_advance(font.glyph_id('M')).max(f32::EPSILON);
+        let scale = PxScale {
+            x: cell_w as f32 / advance,
+            y: cell_h as f32,
+        };
+        let ascent = font.as_scaled(scale).ascent();
+
+        let charset = Charset::parse(charset);
+        let bitmaps = charset
+            .glyphs()
+            .iter()
+            .map(|glyph| {
+                let mut bitmap = vec![0.0; (cell_w * cell_h) as usize];
+                for c in glyph.chars() {
+                    let positioned = font
+                        .glyph_id(c)
+                        .with_scale_and_position(scale, point(0.0, ascent));
+                    let Some(outlined) = font.outline_glyph(positioned) else {
+                        continue;
+                    };
+                    let bounds = outlined.px_bounds();
+                    outlined.draw(|x, y, coverage| {
+                        let px = bounds.min.x as i32 + x as i32;
+                        let py = bounds.min.y as i32 + y as i32;
+                        if (0..cell_w as i32).contains(&px) && (0..cell_h as i32).contains(&py) {
+                            let idx = (py as u32 * cell_w + px as u32) as usize;
+                            bitmap[idx] = (bitmap[idx] + coverage).min(1.0);
+                        }
+                    });
+                }
+                bitmap
+            })
+            .collect();
+
+        Ok(Self {
+            cell_size: (cell_w, cell_h),
+            glyphs: charset.glyphs().to_vec(),
+            bitmaps,
+        })
+    }
+
+    pub fn cell_size(&self) -> (u32, u32) {
+        self.cell_size
+    }
+
+    fn best_match(&self, block: &[f32]) -> &str {
+        self.bitmaps
+            .iter()
+            .zip(&self.glyphs)
+            .map(|(bitmap, glyph)| {
+                let ssd: f32 = bitmap
+                    .iter()
+                    .zip(block)
+                    .map(|(g, b)| (g - b) * (g - b))
+                    .sum();
+                (ssd, glyph)
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, glyph)| glyph.as_str())
+            .unwrap_or(" ")
+    }
+}
+
+// `img` must already be resized to `dimensions` multiplied by the atlas cell size
+pub(crate) fn match_glyphs(
+    img: &DynamicImage,
+    dimensions: (u32, u32),
+    options: &ConversionOptions,
+    atlas: &GlyphAtlas,
+) -> AsciiImage {
+    let rgba = img.to_rgba32f();
+    let (cell_w, cell_h) = atlas.cell_size;
+    let (wr, wg, wb) = options.luma.weights();
+    let charset = Charset::parse(options.charset);
+    let alpha_threshold = options.alpha_threshold.map(|t| t.clamp(0.0, 1.0));
+
+    let mut ascii_img = AsciiImage::create_empty(dimensions);
+    let mut block = vec![0.0; (cell_w * cell_h) as usize];
+    for cy in 0..dimensions.1 {
+        for cx in 0..dimensions.0 {
+            let mut alpha_sum = 0.0;
+            let mut color_sum = [0.0; 3];
+            for y in 0..cell_h {
+                for x in 0..cell_w {
+                    let (px, py) = (cx * cell_w + x, cy * cell_h + y);
+                    let pixel = rgba.get_pixel(px, py);
+                    // Adjusted and dithered like every other mode, per pixel
+                    let luminance = wr * pixel[0] + wg * pixel[1] + wb * pixel[2];
+                    block[(y * cell_w + x) as usize] = options.adjust_brightness(luminance)
+                        + options.dither_offset(px, py, charset.glyphs().len());
+                    alpha_sum += pixel[3];
+                    for c in 0..3 {
+                        color_sum[c] += pixel[c];
+                    }
+                }
+            }
+
+            let cell = &mut ascii_img.data[cy as usize][cx as usize];
+            let mean_alpha = alpha_sum / block.len() as f32;
+            let transparent = alpha_threshold.is_some_and(|threshold| mean_alpha < threshold);
+            ascii_img.transparent[cy as usize][cx as usize] = transparent;
+            ascii_img.colors[cy as usize][cx as usize] =
+                Rgb(color_sum.map(|c| quantize(c / block.len() as f32)));
+            if transparent {
+                *cell = charset.pad(options.transparent_char.encode_utf8(&mut [0; 4]));
+            } else {
+                *cell = atlas.best_match(&block).to_string();
+            }
+        }
+    }
+    ascii_img
+}
@@ -0,0 +1,206 @@
+use std::path::Path;
+
+use image::{DynamicImage, GenericImageView, Rgb};
+
+use crate::{
+    composite_over_background, failures::Failures, load_input, match_tones, output_dimensions,
+    resize_and_convert, slideshow::image_paths, terminal_width, write_art, AsciiImage,
+    ConversionOptions, ProgramError, ProgramParameters,
+};
+
+// Blank cells between neighboring tiles
+const GAP: u32 = 1;
+
+// Narrowest tiles --compare still puts side by side
+const MIN_SIDE_BY_SIDE_CELLS: u32 = 32;
+
+#[derive(Debug, Clone)]
+pub struct Montage<'a> {
+    pub inputs: &'a [String],
+    // Tiles per row; all of them in one row when None
+    pub columns: Option<u32>,
+    // File names under the tiles
+    pub labels: bool,
+}
+
+// Converts every input to a tile of equal width and lays them out in a grid on one
+// character canvas, which is then rendered in the requested format. Inputs that fail
+// to load or convert are left out and summarized afterwards
+pub(crate) fn run(params: &ProgramParameters, montage: &Montage) -> Result<(), ProgramError> {
+    let count = montage.inputs.len().max(1) as u32;
+    let columns = montage.columns.unwrap_or(count).clamp(1, count);
+    let tile_options = tile_options(&params.options, columns);
+    let mut tiles = Vec::with_capacity(montage.inputs.len());
+    let mut failures = Failures::default();
+    for input in montage.inputs {
+        let tile = load_input(params, input).and_then(|img| {
+            let dimensions = output_dimensions(img.dimensions(), &tile_options);
+            convert_tile(input, &img, dimensions, &tile_options)
+        });
+        match tile {
+            Ok(tile) => tiles.push((input.as_str(), tile)),
+            Err(err) => failures.record(input, err),
+        }
+    }
+    if !tiles.is_empty() {
+        lay_out(params, &tiles, columns, montage.labels)?;
+    }
+    failures.report(montage.inputs.len(), params.failures_file)?;
+    if tiles.is_empty() {
+        return Err(ProgramError::EmptyInput);
+    }
+    Ok(())
+}
+
+// Both images at the same cell dimensions (the first one's), side by side or stacked
+// when the terminal is too narrow for that
+pub(crate) fn compare(params: &ProgramParameters, inputs: [&str; 2]) -> Result<(), ProgramError> {
+    let options = &params.options;
+    let images = [
+        load_input(params, inputs[0])?,
+        load_input(params, inputs[1])?,
+    ];
+    let canvas_cells = options.output_width.unwrap_or_else(terminal_width) / options.cell_width();
+    let columns = if canvas_cells.saturating_sub(GAP) / 2 >= MIN_SIDE_BY_SIDE_CELLS {
+        2
+    } else {
+        1
+    };
+    let tile_options = tile_options(options, columns);
+    let dimensions = output_dimensions(images[0].dimensions(), &tile_options);
+    let mut tiles = Vec::with_capacity(2);
+    for (input, img) in inputs.into_iter().zip(&images) {
+        tiles.push((input, convert_tile(input, img, dimensions, &tile_options)?));
+    }
+    lay_out(params, &tiles, columns, true)
+}
+
+// Thumbnails of every image in the input directory, `thumbnail_cells` wide and as many
+// per row as fit the canvas width (--width or the terminal's). Unreadable files are
+// skipped and summarized after the sheet, so one bad file doesn't spoil browsing a
+// whole folder
+pub(crate) fn contact_sheet(
+    params: &ProgramParameters,
+    thumbnail_cells: u32,
+) -> Result<(), ProgramError> {
+    let paths = image_paths(params.input_path)?;
+    let options = &params.options;
+    let cell_width = options.cell_width();
+    let canvas_cells = options.output_width.unwrap_or_else(terminal_width) / cell_width;
+    let thumbnail_cells = thumbnail_cells.clamp(1, canvas_cells.max(1));
+    let columns = ((canvas_cells + GAP) / (thumbnail_cells + GAP)).max(1);
+    let thumbnail_options = ConversionOptions {
+        output_width: Some(thumbnail_cells * cell_width),
+        ..options.clone()
+    };
+
+    let mut tiles = Vec::with_capacity(paths.len());
+    let mut failures = Failures::default();
+    for path in &paths {
+        let input = path.to_string_lossy().into_owned();
+        let thumbnail = load_input(params, &input).and_then(|img| {
+            let dimensions = output_dimensions(img.dimensions(), &thumbnail_options);
+            convert_tile(&input, &img, dimensions, &thumbnail_options)
+        });
+        match thumbnail {
+            Ok(thumbnail) => tiles.push((input, thumbnail)),
+            Err(err) => failures.record(&input, err),
+        }
+    }
+    if !tiles.is_empty() {
+        lay_out(params, &tiles, columns, true)?;
+    }
+    failures.report(paths.len(), params.failures_file)?;
+    if tiles.is_empty() {
+        return Err(ProgramError::EmptyInput);
+    }
+    Ok(())
+}
+
+// Tiles sharing the canvas width (--width or the terminal's) `columns` at a time
+fn tile_options<'a>(options: &ConversionOptions<'a>, columns: u32) -> ConversionOptions<'a> {
+    let cell_width = options.cell_width();
+    let canvas_columns = options.output_width.unwrap_or_else(terminal_width);
+    let tile_cells = (canvas_columns / cell_width).saturating_sub(GAP * (columns - 1)) / columns;
+    ConversionOptions {
+        output_width: Some(tile_cells.max(1) * cell_width),
+        ..options.clone()
+    }
+}
+
+fn convert_tile(
+    input: &str,
+    img: &DynamicImage,
+    dimensions: (u32, u32),
+    options: &ConversionOptions,
+) -> Result<AsciiImage, ProgramError> {
+    for warning in options.validate(img.dimensions())? {
+        eprintln!("Warning: {input}: {warning}");
+    }
+    let img = match_tones(composite_over_background(img, options), options);
+    Ok(resize_and_convert(&img, dimensions, options))
+}
+
+fn lay_out(
+    params: &ProgramParameters,
+    tiles: &[(impl AsRef<str>, AsciiImage)],
+    columns: u32,
+    labels: bool,
+) -> Result<(), ProgramError> {
+    let options = &params.options;
+    let label_rows = labels as u32;
+    let tile_width = tiles
+        .iter()
+        .map(|(_, tile)| tile.dimensions.0)
+        .max()
+        .unwrap_or(1);
+    let row_heights: Vec<u32> = tiles
+        .chunks(columns as usize)
+        .map(|row| {
+            row.iter()
+                .map(|(_, tile)| tile.dimensions.1)
+                .max()
+                .unwrap_or(0)
+                + label_rows
+        })
+        .collect();
+    let canvas_width = tile_width * columns + GAP * (columns - 1);
+    let canvas_height =
+        row_heights.iter().sum::<u32>() + GAP * (row_heights.len() as u32).saturating_sub(1);
+    let mut canvas =
+        AsciiImage::create_blank((canvas_width, canvas_height.max(1)), options.cell_width());
+
+    let ink = ink(options.background);
+    let mut y = 0;
+    for (row, height) in tiles.chunks(columns as usize).zip(&row_heights) {
+        for (i, (input, tile)) in row.iter().enumerate() {
+            let x = i as u32 * (tile_width + GAP);
+            canvas.blit(tile, (x, y));
+            if labels {
+                canvas.write_label(&label(input.as_ref()), (x, y + height - 1), tile_width, ink);
+            }
+        }
+        y += height + GAP;
+    }
+
+    write_art(params, options, &canvas)
+}
+
+// The file name of paths and URLs, the whole input otherwise
+fn label(input: &str) -> String {
+    let trimmed = input.split(['?', '#']).next().unwrap_or(input);
+    Path::new(trimmed)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| input.to_string())
+}
+
+// Labels and borders are drawn in a color readable on the background
+pub(crate) fn ink(Rgb([r, g, b]): Rgb<u8>) -> Rgb<u8> {
+    let luminance = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+    if luminance < 128.0 {
+        Rgb([u8::MAX; 3])
+    } else {
+        Rgb([0; 3])
+    }
+}
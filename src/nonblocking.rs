@@ -0,0 +1,92 @@
+use crate::{
+    convert_bytes,
+    fetch::{request_error, Failure},
+    source::format_from_content_type,
+    ArtOutput, ConversionOptions, Fetched, ProgramError, ReqwestFetcher,
+};
+
+impl ReqwestFetcher {
+    // Same as `Fetcher::fetch`, but on the caller's Tokio runtime instead of
+    // blocking a thread
+    pub async fn fetch_async(&self, url: &str) -> Result<Fetched, ProgramError> {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(self.user_agent())
+            .default_headers(self.default_headers()?);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = self.proxy()? {
+            builder = builder.proxy(proxy);
+        }
+        let client = builder
+            .build()
+            .map_err(|_| ProgramError::FailedToDownload)?;
+        let mut retry = 0;
+        loop {
+            match self.fetch_once_async(&client, url).await {
+                Err((_, failure)) if retry < self.retry.retries && self.retry.applies(failure) => {
+                    tokio::time::sleep(self.retry.delay(retry)).await;
+                    retry += 1;
+                }
+                result => return result.map_err(|(err, _)| err),
+            }
+        }
+    }
+
+    async fn fetch_once_async(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+    ) -> Result<Fetched, (ProgramError, Failure)> {
+        let mut request = client.get(url);
+        if let Some((user, password)) = &self.basic_auth {
+            request = request.basic_auth(user, password.as_ref());
+        }
+        let (cached, validators) = self.revalidation(url);
+        let mut response = request
+            .headers(validators)
+            .send()
+            .await
+            .map_err(request_error)?;
+        let status = response.status();
+        if let Some(fetched) =
+            self.check_response(url, status, response.content_length(), cached)?
+        {
+            return Ok(fetched);
+        }
+
+        let headers = response.headers().clone();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(body_error)? {
+            bytes.extend_from_slice(&chunk);
+            // No need to read further once the cap is exceeded
+            if bytes.len() as u64 > self.max_download_size() {
+                break;
+            }
+        }
+        self.finish_response(url, status, &headers, bytes)
+    }
+}
+
+fn body_error(err: reqwest::Error) -> (ProgramError, Failure) {
+    if err.is_timeout() {
+        (ProgramError::DownloadTimedOut, Failure::Timeout)
+    } else {
+        (ProgramError::DownloadInvalid, Failure::Permanent)
+    }
+}
+
+// Downloads `url` without blocking the async runtime and converts it. Decoding
+// and conversion are CPU bound and still run inline
+pub async fn convert_url(
+    fetcher: &ReqwestFetcher,
+    url: &str,
+    options: &ConversionOptions<'_>,
+) -> Result<ArtOutput, ProgramError> {
+    let fetched = fetcher.fetch_async(url).await?;
+    let format = format_from_content_type(fetched.content_type.as_deref())?;
+    convert_bytes(&fetched.bytes, format, options).map_err(|err| match err {
+        ProgramError::FailedToDecodeInput => ProgramError::DownloadInvalid,
+        err => err,
+    })
+}
@@ -0,0 +1,65 @@
+use std::str::FromStr;
+
+use image::DynamicImage;
+
+// Clockwise rotation applied to the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "90" => Ok(Self::Quarter),
+            "180" => Ok(Self::Half),
+            "270" => Ok(Self::ThreeQuarters),
+            _ => Err(format!(
+                "unsupported rotation (expected 90, 180 or 270): {s}"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flip {
+    // Mirrored left to right
+    Horizontal,
+    // Mirrored top to bottom
+    Vertical,
+}
+
+impl FromStr for Flip {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "h" | "horizontal" => Ok(Self::Horizontal),
+            "v" | "vertical" => Ok(Self::Vertical),
+            _ => Err(format!("unknown flip (expected h or v): {s}")),
+        }
+    }
+}
+
+// Rotates, then mirrors the input, before anything else looks at its pixels
+pub(crate) fn orient(
+    img: DynamicImage,
+    rotation: Option<Rotation>,
+    flip: Option<Flip>,
+) -> DynamicImage {
+    let img = match rotation {
+        Some(Rotation::Quarter) => img.rotate90(),
+        Some(Rotation::Half) => img.rotate180(),
+        Some(Rotation::ThreeQuarters) => img.rotate270(),
+        None => img,
+    };
+    match flip {
+        Some(Flip::Horizontal) => img.fliph(),
+        Some(Flip::Vertical) => img.flipv(),
+        None => img,
+    }
+}
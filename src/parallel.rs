@@ -0,0 +1,22 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// Maps `f` over `items` keeping their order, spread over the rayon thread pool
+// when the parallel feature is enabled
+#[cfg(feature = "parallel")]
+pub(crate) fn map_ordered<T, R, F>(items: Vec<T>, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync + Send,
+{
+    items.into_par_iter().map(f).collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn map_ordered<T, R, F>(items: Vec<T>, f: F) -> Vec<R>
+where
+    F: Fn(T) -> R,
+{
+    items.into_iter().map(f).collect()
+}
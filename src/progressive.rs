@@ -0,0 +1,156 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use image::DynamicImage;
+
+use crate::{
+    render, resize_and_convert, terminal_height, AsciiImage, ColorMode, ConversionOptions,
+    OutputFormat, ProgramError,
+};
+
+// Cells per side of the blocks every pass samples, coarsest first
+const BLOCKS: [u32; 4] = [8, 4, 2, 1];
+
+// Where refinement stops; without either limit it runs to full resolution
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProgressiveBudget {
+    pub time: Option<Duration>,
+    pub bytes: Option<usize>,
+}
+
+impl ProgressiveBudget {
+    fn exceeded(&self, start: Instant, written: usize) -> bool {
+        self.time.is_some_and(|time| start.elapsed() >= time)
+            || self.bytes.is_some_and(|bytes| written >= bytes)
+    }
+}
+
+// Writes a coarse version of the art to the terminal at once, then sharper passes
+// that only rewrite the cells they change, moving the cursor back up to them, until
+// the budget runs out. Returns the art as it was left on screen. Art taller than the
+// terminal can't be revisited once it scrolls, so it's written in full instead
+pub(crate) fn run(
+    img: &DynamicImage,
+    dimensions: (u32, u32),
+    options: &ConversionOptions,
+    budget: ProgressiveBudget,
+) -> Result<AsciiImage, ProgramError> {
+    let start = Instant::now();
+    let options = ConversionOptions {
+        format: OutputFormat::Text,
+        ..options.clone()
+    };
+    let renderer = render::renderer(&options)?;
+    let mut stdout = io::stdout().lock();
+    let blocks: &[u32] = if dimensions.1 >= terminal_height() {
+        &BLOCKS[BLOCKS.len() - 1..]
+    } else {
+        &BLOCKS
+    };
+
+    let mut passes = blocks
+        .iter()
+        .map(|&block| convert_pass(img, dimensions, &options, block));
+    let mut screen = passes.next().ok_or(ProgramError::EmptyInput)?;
+    let output = renderer.render(&screen);
+    let mut written = output.len();
+    write_flushed(&mut stdout, &output)?;
+
+    for pass in passes {
+        if budget.exceeded(start, written) {
+            break;
+        }
+        for y in 0..screen.dimensions.1 as usize {
+            if budget.exceeded(start, written) {
+                break;
+            }
+            let update = row_update(&screen, &pass, y, &options);
+            if update.is_empty() {
+                continue;
+            }
+            written += update.len();
+            write_flushed(&mut stdout, update.as_bytes())?;
+            screen.data[y].clone_from(&pass.data[y]);
+            screen.colors[y].clone_from(&pass.colors[y]);
+            screen.transparent[y].clone_from(&pass.transparent[y]);
+        }
+    }
+    Ok(screen)
+}
+
+fn convert_pass(
+    img: &DynamicImage,
+    (w, h): (u32, u32),
+    options: &ConversionOptions,
+    block: u32,
+) -> AsciiImage {
+    if block == 1 {
+        return resize_and_convert(img, (w, h), options);
+    }
+    let coarse = resize_and_convert(img, (w.div_ceil(block), h.div_ceil(block)), options);
+    upscale(&coarse, (w, h), block)
+}
+
+// Every coarse cell repeated over the block of cells it was sampled from
+fn upscale(coarse: &AsciiImage, (w, h): (u32, u32), block: u32) -> AsciiImage {
+    let mut art = AsciiImage::create_empty((w, h));
+    for y in 0..h as usize {
+        let cy = y / block as usize;
+        for x in 0..w as usize {
+            let cx = x / block as usize;
+            art.data[y][x].clone_from(&coarse.data[cy][cx]);
+            art.colors[y][x] = coarse.colors[cy][cx];
+            art.transparent[y][x] = coarse.transparent[cy][cx];
+        }
+    }
+    art
+}
+
+// Escape sequences rewriting the cells of row `y` that differ from the screen, from
+// and back to the line below the art. Empty when nothing changed
+fn row_update(
+    screen: &AsciiImage,
+    pass: &AsciiImage,
+    y: usize,
+    options: &ConversionOptions,
+) -> String {
+    let colored = options.color != ColorMode::None;
+    let cell_width = options.cell_width() as usize;
+    let mut cells = String::new();
+    let mut in_run = false;
+    for (x, cell) in pass.data[y].iter().enumerate() {
+        let changed =
+            *cell != screen.data[y][x] || (colored && pass.colors[y][x] != screen.colors[y][x]);
+        if !changed {
+            in_run = false;
+            continue;
+        }
+        if !in_run {
+            let _ = write!(cells, "\x1b[{}G", x * cell_width + 1);
+            in_run = true;
+        }
+        let color = match options.palette {
+            Some(palette) => palette.nearest(pass.colors[y][x]),
+            None => pass.colors[y][x],
+        };
+        if let Some(sgr) = options.color.foreground_sgr(color) {
+            let _ = write!(cells, "\x1b[{sgr}m");
+        }
+        cells.push_str(cell);
+    }
+    if cells.is_empty() {
+        return cells;
+    }
+    let up = pass.dimensions.1 as usize - y;
+    let reset = if colored { "\x1b[0m" } else { "" };
+    format!("\x1b[{up}A{cells}{reset}\x1b[{up}B\r")
+}
+
+fn write_flushed(out: &mut impl Write, bytes: &[u8]) -> Result<(), ProgramError> {
+    out.write_all(bytes)
+        .and_then(|_| out.flush())
+        .map_err(|_| ProgramError::FailedToWriteToOutput)
+}
@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+
+use image::ImageFormat;
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+
+use crate::{convert_bytes, ArtOutput, CellMode, ColorMode, ConversionOptions, OutputFormat};
+
+// Converts a path or encoded bytes, e.g. convert("cat.png", width=80, color="256").
+// Unset options keep their defaults
+#[pyfunction]
+#[pyo3(signature = (source, width=None, charset=None, color=None, format=None, mode=None))]
+fn convert(
+    py: Python<'_>,
+    source: &Bound<'_, PyAny>,
+    width: Option<u32>,
+    charset: Option<&str>,
+    color: Option<&str>,
+    format: Option<&str>,
+    mode: Option<&str>,
+) -> PyResult<Conversion> {
+    let (bytes, image_format) = match source.downcast::<PyBytes>() {
+        Ok(bytes) => (bytes.as_bytes().to_vec(), None),
+        Err(_) => {
+            let path: PathBuf = source.extract()?;
+            (std::fs::read(&path)?, ImageFormat::from_path(&path).ok())
+        }
+    };
+    let defaults = ConversionOptions::default();
+    let options = ConversionOptions {
+        output_width: width,
+        charset: charset.unwrap_or(defaults.charset),
+        color: parse_option::<ColorMode>(color)?.unwrap_or(defaults.color),
+        format: parse_option::<OutputFormat>(format)?.unwrap_or(defaults.format),
+        mode: parse_option::<CellMode>(mode)?.unwrap_or(defaults.mode),
+        ..defaults
+    };
+    // Conversion doesn't touch Python objects, so other threads can run meanwhile
+    let output = py
+        .allow_threads(|| convert_bytes(&bytes, image_format, &options))
+        .map_err(|err| PyValueError::new_err(format!("{err:?}")))?;
+    Ok(Conversion { output })
+}
+
+fn parse_option<T: std::str::FromStr<Err = String>>(value: Option<&str>) -> PyResult<Option<T>> {
+    value
+        .map(|value| value.parse().map_err(PyValueError::new_err))
+        .transpose()
+}
+
+// Result of convert(): the art plus what the CLI reports in --stats
+#[pyclass(frozen)]
+struct Conversion {
+    output: ArtOutput,
+}
+
+#[pymethods]
+impl Conversion {
+    // None for binary output formats, use `data` for those
+    #[getter]
+    fn text(&self) -> Option<&str> {
+        self.output.as_text()
+    }
+
+    #[getter]
+    fn data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.output.art)
+    }
+
+    #[getter]
+    fn source_size(&self) -> (u32, u32) {
+        self.output.source_dims
+    }
+
+    #[getter]
+    fn output_size(&self) -> (u32, u32) {
+        self.output.output_dims
+    }
+
+    // Seconds
+    #[getter]
+    fn duration(&self) -> f64 {
+        self.output.duration.as_secs_f64()
+    }
+
+    #[getter]
+    fn warnings(&self) -> Vec<String> {
+        self.output.warnings.clone()
+    }
+
+    fn __str__(&self) -> String {
+        String::from_utf8_lossy(&self.output.art).into_owned()
+    }
+}
+
+#[pymodule]
+fn image2unicodeart(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(convert, m)?)?;
+    m.add_class::<Conversion>()?;
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::{io::Cursor, path::Path};
+
+use image::{DynamicImage, Rgb32FImage};
+use rawloader::{RawImage, RawImageData};
+
+use crate::{DecodeLimits, ProgramError};
+
+// Camera RAW extensions; image has no decoder for them
+const RAW_EXTENSIONS: [&str; 8] = ["arw", "cr2", "dng", "nef", "orf", "pef", "raf", "rw2"];
+
+// Display gamma applied to the linear sensor values
+const GAMMA: f32 = 2.2;
+
+pub(crate) fn is_raw(input: &str) -> bool {
+    let path = input.split(['?', '#']).next().unwrap_or(input);
+    Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| RAW_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+}
+
+// Camera RAW media types, image/x-<vendor>-<extension> such as image/x-canon-cr2
+pub(crate) fn is_raw_mime(mime_type: &str) -> bool {
+    let mime_type = mime_type.to_ascii_lowercase();
+    mime_type
+        .strip_prefix("image/x-")
+        .and_then(|subtype| subtype.rsplit('-').next())
+        .is_some_and(|extension| RAW_EXTENSIONS.contains(&extension))
+}
+
+// Develops the sensor data just enough to preview it: every 2x2 block of the color
+// filter array becomes one pixel (a half resolution demosaic, plenty for cells),
+// gray world white balance evens out the channels, and a display gamma goes on top
+pub(crate) fn develop(bytes: &[u8], limits: DecodeLimits) -> Result<DynamicImage, ProgramError> {
+    let raw = rawloader::decode(&mut Cursor::new(bytes))
+        .map_err(|_| ProgramError::FailedToDecodeInput)?;
+    let [top, right, bottom, left] = raw.crops;
+    let (width, height) = (
+        raw.width.saturating_sub(left + right),
+        raw.height.saturating_sub(top + bottom),
+    );
+    let (w, h) = match raw.cpp {
+        1 => (width / 2, height / 2),
+        3 => (width, height),
+        _ => return Err(ProgramError::FailedToDecodeInput),
+    };
+    if w == 0 || h == 0 {
+        return Err(ProgramError::EmptyInput);
+    }
+    limits.check_dimensions(w as u32, h as u32)?;
+
+    let mut img = Rgb32FImage::new(w as u32, h as u32);
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let (x, y) = (x as usize, y as usize);
+        pixel.0 = match raw.cpp {
+            1 => bin_cfa_block(&raw, left + x * 2, top + y * 2),
+            _ => {
+                let index = ((top + y) * raw.width + left + x) * 3;
+                [0, 1, 2].map(|c| normalized(&raw, index + c, c))
+            }
+        };
+    }
+
+    // Gray world: the scene averages to neutral, so every channel is scaled to
+    // green's mean
+    let mut means = [0.0f64; 3];
+    for pixel in img.pixels() {
+        for c in 0..3 {
+            means[c] += pixel[c] as f64;
+        }
+    }
+    let gains = means.map(|mean| {
+        if mean > 0.0 {
+            (means[1] / mean) as f32
+        } else {
+            1.0
+        }
+    });
+    for pixel in img.pixels_mut() {
+        for c in 0..3 {
+            pixel[c] = (pixel[c] * gains[c]).clamp(0.0, 1.0).powf(1.0 / GAMMA);
+        }
+    }
+    Ok(DynamicImage::ImageRgb32F(img))
+}
+
+// Averages the red, green and blue photosites of the 2x2 block at (x, y)
+fn bin_cfa_block(raw: &RawImage, x: usize, y: usize) -> [f32; 3] {
+    let mut sums = [0.0; 3];
+    let mut counts = [0; 3];
+    for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+        let (px, py) = (x + dx, y + dy);
+        let color = raw.cfa.color_at(py, px);
+        // A fourth color (emerald or a second green) counts as green
+        let channel = if color > 2 { 1 } else { color };
+        sums[channel] += normalized(raw, py * raw.width + px, color);
+        counts[channel] += 1;
+    }
+    [0, 1, 2].map(|c| {
+        if counts[c] > 0 {
+            sums[c] / counts[c] as f32
+        } else {
+            0.0
+        }
+    })
+}
+
+// A sample scaled from the black level to the white level of its channel
+fn normalized(raw: &RawImage, index: usize, color: usize) -> f32 {
+    let value = match &raw.data {
+        RawImageData::Integer(data) => data[index] as f32,
+        RawImageData::Float(data) => return data[index],
+    };
+    let color = color.min(3);
+    let black = raw.blacklevels[color] as f32;
+    let white = raw.whitelevels[color] as f32;
+    if white <= black {
+        return 0.0;
+    }
+    ((value - black) / (white - black)).max(0.0)
+}
@@ -0,0 +1,517 @@
+use std::{fmt::Write, io::Cursor, str::FromStr};
+
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use image::{ImageFormat, Rgb, RgbImage};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{
+    ans::AnsRenderer,
+    color::{ColorMode, Palette},
+    layers::LayersRenderer,
+    webhook::WebhookService,
+    AsciiImage, ConversionOptions, LinkMap, LinkRegion, ProgramError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Html,
+    Svg,
+    Png,
+    Ans,
+    Markdown,
+    // JSON bundle of separate glyph, color and attribute layers
+    Layers,
+    // Plain text posted to a chat webhook as code blocks
+    DiscordWebhook,
+    SlackWebhook,
+}
+
+impl OutputFormat {
+    pub fn is_binary(self) -> bool {
+        matches!(self, OutputFormat::Png | OutputFormat::Ans)
+    }
+
+    pub fn webhook(self) -> Option<WebhookService> {
+        match self {
+            OutputFormat::DiscordWebhook => Some(WebhookService::Discord),
+            OutputFormat::SlackWebhook => Some(WebhookService::Slack),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellMetrics {
+    pub font_size: f32,
+    pub advance: f32,
+    pub line_height: f32,
+}
+
+impl CellMetrics {
+    pub fn from_font_size(font_size: f32) -> Self {
+        Self {
+            font_size,
+            advance: font_size * 0.6,
+            line_height: font_size * 1.2,
+        }
+    }
+}
+
+impl Default for CellMetrics {
+    fn default() -> Self {
+        Self::from_font_size(14.0)
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" | "txt" => Ok(Self::Text),
+            "html" => Ok(Self::Html),
+            "svg" => Ok(Self::Svg),
+            "png" => Ok(Self::Png),
+            "ans" | "ansi" => Ok(Self::Ans),
+            "markdown" | "md" => Ok(Self::Markdown),
+            "layers" => Ok(Self::Layers),
+            "discord-webhook" | "discord" => Ok(Self::DiscordWebhook),
+            "slack-webhook" | "slack" => Ok(Self::SlackWebhook),
+            _ => Err(format!("unknown output format: {s}")),
+        }
+    }
+}
+
+pub(crate) trait Renderer {
+    fn render(&self, art: &AsciiImage) -> Vec<u8>;
+}
+
+pub(crate) fn renderer<'a>(
+    options: &ConversionOptions<'a>,
+) -> Result<Box<dyn Renderer + 'a>, ProgramError> {
+    Ok(match options.format {
+        OutputFormat::Text => Box::new(TextRenderer {
+            overlay: options.overlay,
+            color: options.color,
+            palette: options.palette,
+            background_cells: options.oneline_height.is_some(),
+        }),
+        OutputFormat::Html => Box::new(HtmlRenderer {
+            background: options.background,
+            links: options.link_map,
+            overlay: options.overlay,
+            color: options.color,
+            palette: options.palette,
+        }),
+        OutputFormat::Svg => Box::new(SvgRenderer {
+            background: options.background,
+            metrics: options.cell_metrics,
+            links: options.link_map,
+            color: options.color,
+            palette: options.palette,
+        }),
+        OutputFormat::Markdown => Box::new(MarkdownRenderer),
+        OutputFormat::Layers => Box::new(LayersRenderer {
+            background: options.background,
+        }),
+        // Chat messages can't carry escape sequences
+        OutputFormat::DiscordWebhook | OutputFormat::SlackWebhook => Box::new(TextRenderer {
+            overlay: false,
+            color: ColorMode::None,
+            palette: None,
+            background_cells: false,
+        }),
+        OutputFormat::Ans => Box::new(AnsRenderer {
+            background: options.background,
+        }),
+        OutputFormat::Png => {
+            let font_data = options.font.ok_or(ProgramError::MissingFont)?;
+            let font = FontRef::try_from_slice(font_data).map_err(|_| ProgramError::InvalidFont)?;
+            Box::new(PngRenderer {
+                font,
+                background: options.background,
+                metrics: options.cell_metrics,
+                color: options.color,
+                palette: options.palette,
+            })
+        }
+    })
+}
+
+struct TextRenderer<'a> {
+    overlay: bool,
+    color: ColorMode,
+    palette: Option<&'a Palette>,
+    background_cells: bool,
+}
+
+impl Renderer for TextRenderer<'_> {
+    fn render(&self, art: &AsciiImage) -> Vec<u8> {
+        if !self.overlay && self.color == ColorMode::None {
+            return art.to_text().into_bytes();
+        }
+
+        let cells = (art.dimensions.0 * art.dimensions.1) as usize;
+        let mut text = String::with_capacity(art.text_len() + cells * self.color.sgr_len_hint());
+        for (y, line) in art.data.iter().enumerate() {
+            let mut skipped = 0;
+            let mut current = None;
+            for (x, cell) in line.iter().enumerate() {
+                // Transparent cells become cursor-forward moves so whatever is
+                // already on the terminal shows through
+                if self.overlay && art.transparent[y][x] {
+                    skipped += cell.width();
+                    continue;
+                }
+                if skipped > 0 {
+                    let _ = write!(text, "\x1b[{skipped}C");
+                    skipped = 0;
+                }
+                let mut color = art.colors[y][x];
+                if let Some(palette) = self.palette {
+                    color = palette.nearest(color);
+                }
+                let sgr = if self.background_cells {
+                    self.color.background_sgr(color)
+                } else {
+                    self.color.foreground_sgr(color)
+                };
+                if sgr.is_some() && sgr != current {
+                    let _ = write!(text, "\x1b[{}m", sgr.as_deref().unwrap_or_default());
+                    current = sgr;
+                }
+                if self.background_cells && current.is_some() {
+                    text.extend(std::iter::repeat(' ').take(cell.width()));
+                } else {
+                    text.push_str(cell);
+                }
+            }
+            if current.is_some() {
+                text.push_str("\x1b[0m");
+            }
+            text.push('\n');
+        }
+        text.into_bytes()
+    }
+}
+
+struct MarkdownRenderer;
+
+impl Renderer for MarkdownRenderer {
+    fn render(&self, art: &AsciiImage) -> Vec<u8> {
+        // Control characters are mangled by most Markdown pipelines
+        let body: String = art
+            .to_text()
+            .chars()
+            .map(|c| if c.is_control() && c != '\n' { ' ' } else { c })
+            .collect();
+        // The fence must be longer than any backtick run inside the art
+        let longest_run = body.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+        let fence = "`".repeat(longest_run.max(2) + 1);
+        format!("{fence}text\n{body}{fence}\n").into_bytes()
+    }
+}
+
+struct HtmlRenderer<'a> {
+    background: Rgb<u8>,
+    links: Option<&'a LinkMap>,
+    overlay: bool,
+    color: ColorMode,
+    palette: Option<&'a Palette>,
+}
+
+impl Renderer for HtmlRenderer<'_> {
+    fn render(&self, art: &AsciiImage) -> Vec<u8> {
+        let Rgb([r, g, b]) = self.background;
+        let foreground = ink_color(self.background);
+
+        let mut html = String::with_capacity(art.text_len() + 512);
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str("<title>image2unicodeart</title>\n<style>\n");
+        if self.overlay {
+            html.push_str(&format!(
+                "body {{ background: transparent; color: {foreground}; }}\n"
+            ));
+            // Empty boxes keep the grid aligned without painting over what's beneath
+            html.push_str(".gap { display: inline-block; width: 1ch; }\n");
+        } else {
+            html.push_str(&format!(
+                "body {{ background: #{r:02x}{g:02x}{b:02x}; color: {foreground}; }}\n"
+            ));
+        }
+        html.push_str("pre { font-family: monospace; line-height: 1; }\n");
+        html.push_str("pre a { color: inherit; text-decoration: none; }\n");
+        html.push_str("</style>\n</head>\n<body>\n<pre>\n");
+        for (y, line) in art.data.iter().enumerate() {
+            // Consecutive cells of the same region share one anchor, and consecutive
+            // cells of the same color within it one span
+            let mut open: Option<&LinkRegion> = None;
+            let mut span: Option<Rgb<u8>> = None;
+            for (x, cell) in line.iter().enumerate() {
+                let region = self
+                    .links
+                    .and_then(|links| links.region_at(x as u32, y as u32));
+                if region.map(std::ptr::from_ref) != open.map(std::ptr::from_ref) {
+                    if span.take().is_some() {
+                        html.push_str("</span>");
+                    }
+                    if open.is_some() {
+                        html.push_str("</a>");
+                    }
+                    if let Some(region) = region {
+                        push_anchor(&mut html, region);
+                    }
+                    open = region;
+                }
+                if self.overlay && art.transparent[y][x] {
+                    html.push_str(&"<span class=\"gap\"></span>".repeat(cell.width()));
+                    continue;
+                }
+                let ink = cell_ink(art, x, y, self.color, self.palette);
+                if ink != span {
+                    if span.is_some() {
+                        html.push_str("</span>");
+                    }
+                    if let Some(ink) = ink {
+                        html.push_str(&format!("<span style=\"color: {}\">", hex_color(ink)));
+                    }
+                    span = ink;
+                }
+                push_escaped(&mut html, cell);
+            }
+            if span.is_some() {
+                html.push_str("</span>");
+            }
+            if open.is_some() {
+                html.push_str("</a>");
+            }
+            html.push('\n');
+        }
+        html.push_str("</pre>\n</body>\n</html>\n");
+        html.into_bytes()
+    }
+}
+
+struct SvgRenderer<'a> {
+    background: Rgb<u8>,
+    metrics: CellMetrics,
+    links: Option<&'a LinkMap>,
+    color: ColorMode,
+    palette: Option<&'a Palette>,
+}
+
+impl Renderer for SvgRenderer<'_> {
+    fn render(&self, art: &AsciiImage) -> Vec<u8> {
+        let CellMetrics {
+            font_size,
+            advance,
+            line_height,
+        } = self.metrics;
+        let width = display_columns(art) as f32 * advance;
+        let height = art.data.len() as f32 * line_height;
+
+        let mut svg = String::with_capacity(art.text_len() + 128 * art.data.len() + 512);
+        svg.push_str(&format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n"
+        ));
+        svg.push_str(&format!(
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n",
+            hex_color(self.background)
+        ));
+        svg.push_str(&format!(
+            "<g font-family=\"monospace\" font-size=\"{font_size}\" fill=\"{}\" xml:space=\"preserve\">\n",
+            ink_color(self.background)
+        ));
+        for (row, line) in art.data.iter().enumerate() {
+            let line_columns: usize = line.iter().map(|cell| cell.width()).sum();
+            // Baseline sits at roughly 80% of the line box; textLength pins the advance
+            // so the grid stays aligned regardless of the viewer's monospace font
+            let y = (row as f32 + 0.8) * line_height;
+            svg.push_str(&format!(
+                "<text x=\"0\" y=\"{y}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\">",
+                line_columns as f32 * advance
+            ));
+            // Runs of one color share a tspan
+            let mut span: Option<Rgb<u8>> = None;
+            for (x, cell) in line.iter().enumerate() {
+                let ink = cell_ink(art, x, row, self.color, self.palette);
+                if ink != span {
+                    if span.is_some() {
+                        svg.push_str("</tspan>");
+                    }
+                    if let Some(ink) = ink {
+                        svg.push_str(&format!("<tspan fill=\"{}\">", hex_color(ink)));
+                    }
+                    span = ink;
+                }
+                push_escaped(&mut svg, cell);
+            }
+            if span.is_some() {
+                svg.push_str("</tspan>");
+            }
+            svg.push_str("</text>\n");
+        }
+        svg.push_str("</g>\n");
+        // Transparent hit areas on top of the text make the regions clickable
+        for region in self.links.iter().flat_map(|links| &links.regions) {
+            svg.push_str("<a href=\"");
+            push_escaped(&mut svg, &region.url);
+            svg.push_str("\">");
+            if let Some(title) = &region.title {
+                svg.push_str("<title>");
+                push_escaped(&mut svg, title);
+                svg.push_str("</title>");
+            }
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"transparent\"/></a>\n",
+                region.x as f32 * advance,
+                region.y as f32 * line_height,
+                region.width as f32 * advance,
+                region.height as f32 * line_height
+            ));
+        }
+        svg.push_str("</svg>\n");
+        svg.into_bytes()
+    }
+}
+
+struct PngRenderer<'a> {
+    font: FontRef<'a>,
+    background: Rgb<u8>,
+    metrics: CellMetrics,
+    color: ColorMode,
+    palette: Option<&'a Palette>,
+}
+
+impl Renderer for PngRenderer<'_> {
+    fn render(&self, art: &AsciiImage) -> Vec<u8> {
+        let CellMetrics {
+            font_size,
+            advance,
+            line_height,
+        } = self.metrics;
+        let width = (display_columns(art) as f32 * advance).ceil() as u32;
+        let height = (art.data.len() as f32 * line_height).ceil() as u32;
+        let mut img = RgbImage::from_pixel(width.max(1), height.max(1), self.background);
+
+        let default_ink = if ink_color(self.background) == "#ffffff" {
+            Rgb([u8::MAX; 3])
+        } else {
+            Rgb([0; 3])
+        };
+        let scale = PxScale::from(font_size);
+        let scaled = self.font.as_scaled(scale);
+        // Center the font's line box vertically within the cell
+        let baseline_offset = (line_height - scaled.height()) / 2.0 + scaled.ascent();
+
+        for (row, line) in art.data.iter().enumerate() {
+            let mut column = 0;
+            for (x, cell) in line.iter().enumerate() {
+                let ink = cell_ink(art, x, row, self.color, self.palette).unwrap_or(default_ink);
+                // Zero-width characters of a cluster (combining marks, variation
+                // selectors, joiners) are drawn over the character they follow
+                let (mut base, mut next) = (column, column);
+                for c in cell.chars() {
+                    let char_width = c.width().unwrap_or(0);
+                    if char_width > 0 {
+                        base = next;
+                        next += char_width;
+                    }
+                    let position = point(
+                        base as f32 * advance,
+                        row as f32 * line_height + baseline_offset,
+                    );
+                    let glyph = self
+                        .font
+                        .glyph_id(c)
+                        .with_scale_and_position(scale, position);
+                    if let Some(outlined) = self.font.outline_glyph(glyph) {
+                        let bounds = outlined.px_bounds();
+                        outlined.draw(|x, y, coverage| {
+                            let px = bounds.min.x as i32 + x as i32;
+                            let py = bounds.min.y as i32 + y as i32;
+                            if px < 0 || py < 0 || px as u32 >= width || py as u32 >= height {
+                                return;
+                            }
+                            let pixel = img.get_pixel_mut(px as u32, py as u32);
+                            for ch in 0..3 {
+                                let value =
+                                    pixel[ch] as f32 * (1.0 - coverage) + ink[ch] as f32 * coverage;
+                                pixel[ch] = value.round() as u8;
+                            }
+                        });
+                    }
+                }
+                column += cell.width().max(1);
+            }
+        }
+
+        let mut png = Vec::new();
+        // Encoding an in-memory RGB buffer as PNG can't fail
+        let _ = img.write_to(&mut Cursor::new(&mut png), ImageFormat::Png);
+        png
+    }
+}
+
+fn display_columns(art: &AsciiImage) -> usize {
+    art.data
+        .iter()
+        .map(|line| line.iter().map(|cell| cell.width()).sum::<usize>())
+        .max()
+        .unwrap_or(0)
+}
+
+pub(crate) fn hex_color(Rgb([r, g, b]): Rgb<u8>) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+// Glyph density assumes light ink on the background, so pick a contrasting ink
+fn ink_color(Rgb([r, g, b]): Rgb<u8>) -> &'static str {
+    let luminance = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+    if luminance < 128.0 {
+        "#ffffff"
+    } else {
+        "#000000"
+    }
+}
+
+// Ink of the cell at (x, y) when output is colored, the palette snapped and limited to
+// what the color mode shows
+fn cell_ink(
+    art: &AsciiImage,
+    x: usize,
+    y: usize,
+    color: ColorMode,
+    palette: Option<&Palette>,
+) -> Option<Rgb<u8>> {
+    let mut ink = art.colors[y][x];
+    if let Some(palette) = palette {
+        ink = palette.nearest(ink);
+    }
+    color.displayed(ink)
+}
+
+fn push_anchor(html: &mut String, region: &LinkRegion) {
+    html.push_str("<a href=\"");
+    push_escaped(html, &region.url);
+    html.push('"');
+    if let Some(title) = &region.title {
+        html.push_str(" title=\"");
+        push_escaped(html, title);
+        html.push('"');
+    }
+    html.push('>');
+}
+
+fn push_escaped(html: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            c => html.push(c),
+        }
+    }
+}
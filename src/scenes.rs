@@ -0,0 +1,81 @@
+use image::RgbaImage;
+
+use crate::{
+    tones::{luminance_histogram, LEVELS},
+    Luma,
+};
+
+const CUT_BINS: usize = 32;
+
+// Difference between consecutive frames' normalized histograms (0..2) that
+// starts a new scene
+const SCENE_CUT: f32 = 0.6;
+
+// Share of the darkest and of the brightest pixels left out of a scene's range,
+// so a few specular highlights don't decide it
+const CLIP: f32 = 0.02;
+
+// Narrowest range a scene is stretched from, so flat scenes don't turn noise into
+// the full ramp
+const MIN_RANGE: f32 = 1.0 / 16.0;
+
+// Brightness range (0..1) of every frame's scene. Ranges are measured over whole
+// scenes rather than single frames, so the levels only jump at cuts
+pub(crate) fn brightness_ranges(frames: &[RgbaImage], luma: Luma) -> Vec<(f32, f32)> {
+    let histograms: Vec<[u64; LEVELS]> = frames
+        .iter()
+        .map(|frame| luminance_histogram(frame, luma))
+        .collect();
+    let mut ranges = Vec::with_capacity(frames.len());
+    let mut start = 0;
+    for end in 1..=histograms.len() {
+        if end < histograms.len() && !is_cut(&histograms[end - 1], &histograms[end]) {
+            continue;
+        }
+        let mut pooled = [0; LEVELS];
+        for histogram in &histograms[start..end] {
+            for (total, count) in pooled.iter_mut().zip(histogram) {
+                *total += count;
+            }
+        }
+        ranges.extend(std::iter::repeat(clipped_range(&pooled)).take(end - start));
+        start = end;
+    }
+    ranges
+}
+
+fn is_cut(a: &[u64; LEVELS], b: &[u64; LEVELS]) -> bool {
+    let coarse = |histogram: &[u64; LEVELS]| {
+        let total = histogram.iter().sum::<u64>().max(1) as f32;
+        let mut bins = [0.0; CUT_BINS];
+        for (level, count) in histogram.iter().enumerate() {
+            bins[level * CUT_BINS / LEVELS] += *count as f32 / total;
+        }
+        bins
+    };
+    let (a, b) = (coarse(a), coarse(b));
+    let difference: f32 = a.iter().zip(&b).map(|(a, b)| (a - b).abs()).sum();
+    difference > SCENE_CUT
+}
+
+fn clipped_range(histogram: &[u64; LEVELS]) -> (f32, f32) {
+    let total = histogram.iter().sum::<u64>();
+    let clip = (total as f32 * CLIP) as u64;
+    let percentile = |levels: &mut dyn Iterator<Item = usize>| {
+        let mut seen = 0;
+        levels
+            .find(|&level| {
+                seen += histogram[level];
+                seen > clip
+            })
+            .unwrap_or(0)
+    };
+    let low = percentile(&mut (0..LEVELS)) as f32 / (LEVELS - 1) as f32;
+    let high = percentile(&mut (0..LEVELS).rev()) as f32 / (LEVELS - 1) as f32;
+    if total == 0 || high - low < MIN_RANGE {
+        let center = ((low + high) / 2.0).clamp(MIN_RANGE / 2.0, 1.0 - MIN_RANGE / 2.0);
+        (center - MIN_RANGE / 2.0, center + MIN_RANGE / 2.0)
+    } else {
+        (low, high)
+    }
+}
@@ -0,0 +1,77 @@
+use crate::{charset_preset, ColorMode, Crop, Dither, Length, ProgramError};
+
+// Settings replayed from a script recorded by --view or --tune, one "setting value"
+// step per line (later steps override earlier ones, # starts a comment):
+//
+//     preset ascii
+//     contrast 1.20
+//     region 0.1050 0.3200 0.4100 0.3100
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Session {
+    pub charset: Option<String>,
+    pub symbol_aspect_ratio: Option<f32>,
+    pub contrast: Option<f32>,
+    pub gamma: Option<f32>,
+    pub dither: Option<Dither>,
+    pub color: Option<ColorMode>,
+    // Part of the image the view showed: x, y, width and height as shares (0..1) of
+    // the image's width and height
+    pub region: Option<(f32, f32, f32, f32)>,
+}
+
+impl Session {
+    pub fn parse(script: &str) -> Option<Self> {
+        let mut session = Self::default();
+        for line in script.lines() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let (setting, value) = line.split_once(' ').unwrap_or((line, ""));
+            let number = || value.trim().parse::<f32>().ok().filter(|n| n.is_finite());
+            match setting {
+                "preset" => session.charset = Some(charset_preset(value.trim())?.to_string()),
+                // The rest of the line as is, since ramps often start with a space
+                "charset" => session.charset = Some(value.to_string()),
+                "symbol-aspect-ratio" => session.symbol_aspect_ratio = Some(number()?),
+                "contrast" => session.contrast = Some(number()?),
+                "gamma" => session.gamma = Some(number()?),
+                "dither" => session.dither = Some(value.trim().parse().ok()?),
+                "color" => session.color = Some(value.trim().parse().ok()?),
+                "region" => {
+                    let shares: Vec<f32> = value
+                        .split_whitespace()
+                        .map(|v| v.parse().ok().filter(|v: &f32| (0.0..=1.0).contains(v)))
+                        .collect::<Option<_>>()?;
+                    let [x, y, width, height] = shares[..] else {
+                        return None;
+                    };
+                    if width <= 0.0 || height <= 0.0 {
+                        return None;
+                    }
+                    session.region = Some((x, y, width.min(1.0 - x), height.min(1.0 - y)));
+                }
+                _ => return None,
+            }
+        }
+        Some(session)
+    }
+
+    // The part of the image the recorded view showed, None when it showed all of it
+    pub fn crop(&self) -> Option<Crop> {
+        let (x, y, width, height) = self
+            .region
+            .filter(|&region| region != (0.0, 0.0, 1.0, 1.0))?;
+        Some(Crop {
+            x: Length::Percent(x * 100.0),
+            y: Length::Percent(y * 100.0),
+            width: Length::Percent(width * 100.0),
+            height: Length::Percent(height * 100.0),
+        })
+    }
+}
+
+pub fn read_session(path: &str) -> Result<Session, ProgramError> {
+    let script = std::fs::read_to_string(path).map_err(|_| ProgramError::InvalidSession)?;
+    Session::parse(&script).ok_or(ProgramError::InvalidSession)
+}
@@ -0,0 +1,173 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    net::TcpStream,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use crate::ProgramError;
+
+// Where rendered output goes. Nothing is final until `finish` succeeds, so a
+// conversion failing half way never leaves partial output behind where possible.
+pub trait OutputSink: Write {
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+// "-" or no output for stdout, "clipboard:" for the system clipboard,
+// "tcp://host:port" for a socket and anything else for a file, which is either
+// replaced atomically or, with `append`, extended in place
+pub fn open_sink(output: Option<&str>, append: bool) -> Result<Box<dyn OutputSink>, ProgramError> {
+    let sink: Box<dyn OutputSink> = match output {
+        None | Some("-") => Box::new(StdoutSink(io::stdout())),
+        Some("clipboard:") => Box::new(ClipboardSink(Vec::new())),
+        Some(output) => match output.strip_prefix("tcp://") {
+            Some(address) => Box::new(
+                TcpStream::connect(address).map_err(|_| ProgramError::FailedToWriteToOutput)?,
+            ),
+            None if append => Box::new(BufWriter::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(output)
+                    .map_err(|_| ProgramError::FailedToWriteToOutput)?,
+            )),
+            None => Box::new(
+                AtomicFileSink::create(Path::new(output))
+                    .map_err(|_| ProgramError::FailedToWriteToOutput)?,
+            ),
+        },
+    };
+    Ok(sink)
+}
+
+pub struct StdoutSink(pub io::Stdout);
+
+impl Write for StdoutSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl OutputSink for StdoutSink {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+impl OutputSink for TcpStream {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.flush()?;
+        self.shutdown(std::net::Shutdown::Write)
+    }
+}
+
+impl OutputSink for BufWriter<File> {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+// Writes next to the target and renames over it once complete, so readers never
+// see a truncated file; the temporary file is removed if the sink is dropped
+pub struct AtomicFileSink {
+    file: Option<BufWriter<File>>,
+    temp_path: PathBuf,
+    path: PathBuf,
+}
+
+impl AtomicFileSink {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp_path = path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+        let file = File::create(&temp_path)?;
+        Ok(Self {
+            file: Some(BufWriter::new(file)),
+            temp_path,
+            path: path.to_path_buf(),
+        })
+    }
+
+    fn file(&mut self) -> io::Result<&mut BufWriter<File>> {
+        self.file
+            .as_mut()
+            .ok_or_else(|| io::Error::other("output already finished"))
+    }
+}
+
+impl Write for AtomicFileSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file()?.flush()
+    }
+}
+
+impl OutputSink for AtomicFileSink {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        let file = self
+            .file
+            .take()
+            .ok_or_else(|| io::Error::other("output already finished"))?;
+        file.into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+        std::fs::rename(&self.temp_path, &self.path)
+    }
+}
+
+impl Drop for AtomicFileSink {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            let _ = std::fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+// Collects the output and hands it to the platform's clipboard tool on finish
+pub struct ClipboardSink(Vec<u8>);
+
+const CLIPBOARD_COMMANDS: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+    &["pbcopy"],
+    &["clip.exe"],
+];
+
+impl Write for ClipboardSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl OutputSink for ClipboardSink {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        for command in CLIPBOARD_COMMANDS {
+            let Ok(mut child) = Command::new(command[0])
+                .args(&command[1..])
+                .stdin(Stdio::piped())
+                .spawn()
+            else {
+                continue;
+            };
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(&self.0)?;
+            }
+            if child.wait()?.success() {
+                return Ok(());
+            }
+        }
+        Err(io::Error::other("no clipboard command available"))
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    collections::hash_map::RandomState,
+    fs,
+    hash::{BuildHasher, Hasher},
+    io::{self, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use image::{DynamicImage, GenericImageView, ImageFormat};
+
+use crate::{
+    convert_image, decode_bytes, terminal_height, terminal_width, ConversionOptions, ProgramError,
+    ProgramParameters,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Slideshow {
+    pub delay: Duration,
+    // Random order instead of sorted by file name
+    pub shuffle: bool,
+}
+
+enum Step {
+    Next,
+    Previous,
+    Quit,
+}
+
+// Shows every image in the input directory fitted to the terminal, advancing after
+// the delay or, with the viewer feature, on a key press (left/p goes back, q quits)
+pub(crate) fn run(params: &ProgramParameters, slideshow: Slideshow) -> Result<(), ProgramError> {
+    let mut paths = image_paths(params.input_path)?;
+    if paths.is_empty() {
+        return Err(ProgramError::EmptyInput);
+    }
+    if slideshow.shuffle {
+        shuffle(&mut paths);
+    }
+
+    let mut stdout = io::stdout().lock();
+    let (mut index, mut forward) = (0, true);
+    while index < paths.len() {
+        let path = &paths[index];
+        let output = fs::read(path)
+            .map_err(|_| ProgramError::InvalidInputPath)
+            .and_then(|bytes| {
+                decode_bytes(
+                    &bytes,
+                    ImageFormat::from_path(path).ok(),
+                    params.isolate_decoding,
+                    params.decode_limits,
+                )
+            })
+            .and_then(|img| convert_image(&img, &fitted(&img, &params.options)));
+        let step = match output {
+            Ok(output) => {
+                write!(stdout, "\x1b[2J\x1b[H")
+                    .and_then(|_| stdout.write_all(&output.art))
+                    .and_then(|_| stdout.flush())
+                    .map_err(|_| ProgramError::FailedToWriteToOutput)?;
+                wait(slideshow.delay)?
+            }
+            // Unreadable or unconvertible files are skipped in the direction the show is going
+            Err(_) => {
+                eprintln!("Warning: skipping {}", path.display());
+                if !forward && index > 0 {
+                    Step::Previous
+                } else {
+                    Step::Next
+                }
+            }
+        };
+        match step {
+            Step::Next => (index, forward) = (index + 1, true),
+            Step::Previous => (index, forward) = (index.saturating_sub(1), false),
+            Step::Quit => break,
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn image_paths(dir: &str) -> Result<Vec<PathBuf>, ProgramError> {
+    let entries = fs::read_dir(dir).map_err(|_| ProgramError::InvalidInputPath)?;
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && ImageFormat::from_path(path).is_ok())
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+// Fisher-Yates with a xorshift generator seeded by std's random hasher keys
+fn shuffle(paths: &mut [PathBuf]) {
+    let mut state = RandomState::new().build_hasher().finish() | 1;
+    for i in (1..paths.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        paths.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+}
+
+// Widest output (up to --width, if given) that shows the whole image, leaving a row
+// for the cursor
+fn fitted<'a>(img: &DynamicImage, options: &ConversionOptions<'a>) -> ConversionOptions<'a> {
+    let (w, h) = img.dimensions();
+    let rows = terminal_height().saturating_sub(1).max(1);
+    let fit = (rows as f32 * w as f32 / (h as f32 * options.symbol_aspect_ratio)) as u32;
+    let columns = options.output_width.unwrap_or_else(terminal_width);
+    ConversionOptions {
+        output_width: Some(columns.min(fit).max(1)),
+        ..options.clone()
+    }
+}
+
+#[cfg(feature = "viewer")]
+fn wait(delay: Duration) -> Result<Step, ProgramError> {
+    use crossterm::terminal;
+
+    terminal::enable_raw_mode().map_err(|_| ProgramError::FailedToWriteToOutput)?;
+    let step = wait_for_key(delay);
+    let _ = terminal::disable_raw_mode();
+    step.map_err(|_| ProgramError::FailedToWriteToOutput)
+}
+
+#[cfg(feature = "viewer")]
+fn wait_for_key(delay: Duration) -> io::Result<Step> {
+    use std::time::Instant;
+
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+
+    let deadline = Instant::now() + delay;
+    loop {
+        if !event::poll(deadline.saturating_duration_since(Instant::now()))? {
+            return Ok(Step::Next);
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        match key.code {
+            // Raw mode turns Ctrl-C into a key press
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(Step::Quit)
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(Step::Quit),
+            KeyCode::Left | KeyCode::Backspace | KeyCode::Char('p') => return Ok(Step::Previous),
+            KeyCode::Right | KeyCode::Char(' ') | KeyCode::Enter | KeyCode::Char('n') => {
+                return Ok(Step::Next)
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(not(feature = "viewer"))]
+fn wait(delay: Duration) -> Result<Step, ProgramError> {
+    std::thread::sleep(delay);
+    Ok(Step::Next)
+}
@@ -0,0 +1,253 @@
+use std::io::Read;
+
+use image::{DynamicImage, ImageFormat};
+
+use crate::{generators, Fetcher, Generator, ProgramError};
+
+pub enum SourceData {
+    // Still encoded, decoded (possibly in isolation) by the caller
+    Encoded {
+        bytes: Vec<u8>,
+        format: Option<ImageFormat>,
+    },
+    Decoded(DynamicImage),
+}
+
+// A kind of input (file, URL, stdin, ...) selected by the input string
+pub trait ImageSource: std::fmt::Debug {
+    fn accepts(&self, input: &str) -> bool;
+
+    fn open(&self, input: &str, fetcher: &dyn Fetcher) -> Result<SourceData, ProgramError>;
+
+    // Error reported when the opened bytes don't decode
+    fn decode_error(&self) -> ProgramError {
+        ProgramError::FailedToDecodeInput
+    }
+}
+
+// Sources are tried in registration order, so register specific ones first
+#[derive(Debug)]
+pub struct SourceRegistry {
+    sources: Vec<Box<dyn ImageSource>>,
+}
+
+impl SourceRegistry {
+    pub fn new() -> Self {
+        Self {
+            sources: Vec::new(),
+        }
+    }
+
+    // Stdin ("-"), data: URIs, URLs, procedural ("generate:<name>") and, for
+    // anything else, files
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(StdinSource));
+        registry.register(Box::new(DataUriSource));
+        registry.register(Box::new(UrlSource));
+        registry.register(Box::new(ProceduralSource));
+        registry.register(Box::new(FileSource));
+        registry
+    }
+
+    pub fn register(&mut self, source: Box<dyn ImageSource>) {
+        self.sources.push(source);
+    }
+
+    pub fn resolve(&self, input: &str) -> Result<&dyn ImageSource, ProgramError> {
+        self.sources
+            .iter()
+            .find(|source| source.accepts(input))
+            .map(|source| source.as_ref())
+            .ok_or(ProgramError::InvalidInputPath)
+    }
+}
+
+impl Default for SourceRegistry {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+#[derive(Debug)]
+pub struct FileSource;
+
+impl ImageSource for FileSource {
+    fn accepts(&self, _input: &str) -> bool {
+        true
+    }
+
+    fn open(&self, input: &str, _fetcher: &dyn Fetcher) -> Result<SourceData, ProgramError> {
+        let bytes = std::fs::read(input).map_err(|_| ProgramError::InvalidInputPath)?;
+        Ok(SourceData::Encoded {
+            bytes,
+            format: ImageFormat::from_path(input).ok(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct UrlSource;
+
+impl ImageSource for UrlSource {
+    fn accepts(&self, input: &str) -> bool {
+        input.starts_with("http://") || input.starts_with("https://")
+    }
+
+    fn open(&self, input: &str, fetcher: &dyn Fetcher) -> Result<SourceData, ProgramError> {
+        let fetched = fetcher.fetch(input)?;
+        let format = format_from_content_type(fetched.content_type.as_deref())?;
+        Ok(SourceData::Encoded {
+            bytes: fetched.bytes,
+            format,
+        })
+    }
+
+    fn decode_error(&self) -> ProgramError {
+        ProgramError::DownloadInvalid
+    }
+}
+
+// A Content-Type that isn't an image means the URL doesn't point at one
+pub(crate) fn format_from_content_type(
+    content_type: Option<&str>,
+) -> Result<Option<ImageFormat>, ProgramError> {
+    Ok(match content_type {
+        // Sniffed from the markup, image has no SVG format
+        #[cfg(feature = "svg")]
+        Some(content_type) if crate::svg::is_svg_mime(content_type) => None,
+        // Developed once rawloader recognizes the bytes
+        #[cfg(feature = "raw")]
+        Some(content_type) if crate::raw::is_raw_mime(content_type) => None,
+        Some(content_type) => {
+            Some(ImageFormat::from_mime_type(content_type).ok_or(ProgramError::DownloadInvalid)?)
+        }
+        None => None,
+    })
+}
+
+// Inline images such as "data:image/png;base64,iVBOR...", as copied from browsers
+#[derive(Debug)]
+pub struct DataUriSource;
+
+impl ImageSource for DataUriSource {
+    fn accepts(&self, input: &str) -> bool {
+        input.starts_with("data:")
+    }
+
+    fn open(&self, input: &str, _fetcher: &dyn Fetcher) -> Result<SourceData, ProgramError> {
+        let (header, payload) = input["data:".len()..]
+            .split_once(',')
+            .ok_or(ProgramError::InvalidDataUri)?;
+        let mut parameters = header.split(';');
+        let mime_type = parameters.next().unwrap_or_default();
+        let base64 = parameters.any(|parameter| parameter.eq_ignore_ascii_case("base64"));
+
+        // An empty media type defaults to text/plain, which is no image either
+        let format = match mime_type {
+            "" => None,
+            #[cfg(feature = "svg")]
+            mime_type if crate::svg::is_svg_mime(mime_type) => None,
+            #[cfg(feature = "raw")]
+            mime_type if crate::raw::is_raw_mime(mime_type) => None,
+            mime_type => {
+                Some(ImageFormat::from_mime_type(mime_type).ok_or(ProgramError::InvalidDataUri)?)
+            }
+        };
+        let bytes = if base64 {
+            decode_base64(&percent_decode(payload)?)?
+        } else {
+            percent_decode(payload)?
+        };
+        Ok(SourceData::Encoded { bytes, format })
+    }
+
+    fn decode_error(&self) -> ProgramError {
+        ProgramError::InvalidDataUri
+    }
+}
+
+fn percent_decode(s: &str) -> Result<Vec<u8>, ProgramError> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail.get(..2).ok_or(ProgramError::InvalidDataUri)?;
+            let hex = std::str::from_utf8(hex).map_err(|_| ProgramError::InvalidDataUri)?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| ProgramError::InvalidDataUri)?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    Ok(bytes)
+}
+
+// Standard and URL-safe alphabets, padding optional, whitespace ignored
+fn decode_base64(encoded: &[u8]) -> Result<Vec<u8>, ProgramError> {
+    let mut bytes = Vec::with_capacity(encoded.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &c in encoded {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return Err(ProgramError::InvalidDataUri),
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Ok(bytes)
+}
+
+#[derive(Debug)]
+pub struct StdinSource;
+
+impl ImageSource for StdinSource {
+    fn accepts(&self, input: &str) -> bool {
+        input == "-"
+    }
+
+    fn open(&self, _input: &str, _fetcher: &dyn Fetcher) -> Result<SourceData, ProgramError> {
+        let mut bytes = Vec::new();
+        std::io::stdin()
+            .lock()
+            .read_to_end(&mut bytes)
+            .map_err(|_| ProgramError::InvalidInputPath)?;
+        Ok(SourceData::Encoded {
+            bytes,
+            format: None,
+        })
+    }
+}
+
+// The first frame of a generator, e.g. "generate:plasma"
+#[derive(Debug)]
+pub struct ProceduralSource;
+
+impl ImageSource for ProceduralSource {
+    fn accepts(&self, input: &str) -> bool {
+        input.starts_with("generate:")
+    }
+
+    fn open(&self, input: &str, _fetcher: &dyn Fetcher) -> Result<SourceData, ProgramError> {
+        let generator: Generator = input["generate:".len()..]
+            .parse()
+            .map_err(|_| ProgramError::InvalidInputPath)?;
+        let frame = generators::generate_frames(generator, 1)
+            .pop()
+            .ok_or(ProgramError::EmptyInput)?;
+        Ok(SourceData::Decoded(DynamicImage::ImageRgba8(frame)))
+    }
+}
@@ -0,0 +1,149 @@
+use std::{collections::HashMap, fmt::Write};
+
+use image::DynamicImage;
+
+use crate::{render, AsciiImage, CellMode, Charset, ConversionOptions, Luma, OutputFormat};
+
+pub const CELL_STATISTICS_CHANNELS: usize = 7;
+
+#[derive(Debug, Clone)]
+pub struct CellStatistics {
+    pub columns: u32,
+    pub rows: u32,
+    // Row-major cells, each holding [mean, min, max, std, r, g, b] in the 0..1 range
+    pub data: Vec<f32>,
+}
+
+impl CellStatistics {
+    pub fn cell(&self, x: u32, y: u32) -> &[f32] {
+        let start = (y * self.columns + x) as usize * CELL_STATISTICS_CHANNELS;
+        &self.data[start..start + CELL_STATISTICS_CHANNELS]
+    }
+}
+
+pub fn cell_statistics(img: &DynamicImage, grid: (u32, u32), luma: Luma) -> CellStatistics {
+    let (columns, rows) = grid;
+    let rgba = img.to_rgba8();
+    let (w, h) = rgba.dimensions();
+    let (wr, wg, wb) = luma.weights();
+
+    let mut data = Vec::with_capacity((columns * rows) as usize * CELL_STATISTICS_CHANNELS);
+    for cy in 0..rows {
+        let (y0, y1) = cell_span(cy, rows, h);
+        for cx in 0..columns {
+            let (x0, x1) = cell_span(cx, columns, w);
+
+            let mut sum = 0.0;
+            let mut sum_sq = 0.0;
+            let mut min = f32::MAX;
+            let mut max = f32::MIN;
+            let mut color_sum = [0.0; 3];
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let pixel = rgba.get_pixel(x, y);
+                    let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|c| c as f32 / 255.0);
+                    let brightness = wr * r + wg * g + wb * b;
+                    sum += brightness;
+                    sum_sq += brightness * brightness;
+                    min = min.min(brightness);
+                    max = max.max(brightness);
+                    color_sum[0] += r;
+                    color_sum[1] += g;
+                    color_sum[2] += b;
+                }
+            }
+
+            let count = ((x1 - x0) * (y1 - y0)).max(1) as f32;
+            let mean = sum / count;
+            let std = (sum_sq / count - mean * mean).max(0.0).sqrt();
+            data.extend_from_slice(&[mean, min, max, std]);
+            data.extend(color_sum.map(|c| c / count));
+        }
+    }
+
+    CellStatistics {
+        columns,
+        rows,
+        data,
+    }
+}
+
+fn cell_span(index: u32, cells: u32, pixels: u32) -> (u32, u32) {
+    let start = (index as u64 * pixels as u64 / cells as u64) as u32;
+    let end = ((index as u64 + 1) * pixels as u64 / cells as u64) as u32;
+    (
+        start.min(pixels.saturating_sub(1)),
+        end.max(start + 1).min(pixels),
+    )
+}
+
+// Glyph usage, output sizes and the brightness range the art actually uses, for
+// tuning charsets to an image (--stats)
+pub(crate) fn art_report(art: &AsciiImage, options: &ConversionOptions) -> String {
+    let (w, h) = art.dimensions;
+    let total = (w as usize * h as usize).max(1);
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for cell in art.data.iter().flatten() {
+        *counts.entry(cell.as_str()).or_default() += 1;
+    }
+    let mut usage: Vec<(&str, usize)> = counts
+        .iter()
+        .map(|(&glyph, &count)| (glyph, count))
+        .collect();
+    usage.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let mut report = String::new();
+    let _ = writeln!(report, "Cells: {} ({w}x{h})", w * h);
+    report.push_str("Glyph usage:\n");
+    for (glyph, count) in &usage {
+        let share = *count as f32 * 100.0 / total as f32;
+        let _ = writeln!(report, "  '{glyph}' {count} ({share:.1}%)");
+    }
+
+    // Other modes don't pick glyphs by brightness
+    if options.mode == CellMode::Glyphs {
+        let charset = Charset::parse(options.charset);
+        let range = |weights: &mut dyn Iterator<Item = f32>| {
+            weights.fold((f32::MAX, f32::MIN), |(low, high), weight| {
+                (low.min(weight), high.max(weight))
+            })
+        };
+        let levels = charset.glyphs().iter().zip(charset.weights());
+        let used: Vec<f32> = levels
+            .clone()
+            .filter(|(glyph, _)| counts.contains_key(glyph.as_str()))
+            .map(|(_, weight)| *weight)
+            .collect();
+        let (low, high) = range(&mut used.iter().copied());
+        let (charset_low, charset_high) = range(&mut levels.map(|(_, weight)| *weight));
+        let _ = writeln!(
+            report,
+            "Levels used: {} of {}, brightness {low:.2}..{high:.2} \
+             of {charset_low:.2}..{charset_high:.2}",
+            used.len(),
+            charset.glyphs().len()
+        );
+    }
+
+    report.push_str("Output bytes:\n");
+    let formats = [
+        ("text", OutputFormat::Text),
+        ("html", OutputFormat::Html),
+        ("svg", OutputFormat::Svg),
+        ("markdown", OutputFormat::Markdown),
+        ("ans", OutputFormat::Ans),
+        ("layers", OutputFormat::Layers),
+        ("png", OutputFormat::Png),
+    ];
+    for (name, format) in formats {
+        let options = ConversionOptions {
+            format,
+            ..options.clone()
+        };
+        // PNG is left out without a font
+        if let Ok(renderer) = render::renderer(&options) {
+            let _ = writeln!(report, "  {name} {}", renderer.render(art).len());
+        }
+    }
+    report
+}
@@ -0,0 +1,213 @@
+use std::str::FromStr;
+
+use ab_glyph::{Font, FontRef};
+use image::{DynamicImage, Rgb, Rgba};
+
+use crate::{quantize, AsciiImage, Charset, ConversionOptions};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellMode {
+    Glyphs,
+    Emoji,
+    HalfBlock,
+    Braille,
+    // Unicode 16 block octants, the solid counterpart of braille
+    Octant,
+    // Braille in detailed cells, charset glyphs in flat ones
+    Adaptive,
+}
+
+impl FromStr for CellMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "glyphs" => Ok(Self::Glyphs),
+            "emoji" => Ok(Self::Emoji),
+            "halfblock" | "half-block" => Ok(Self::HalfBlock),
+            "braille" => Ok(Self::Braille),
+            "octant" => Ok(Self::Octant),
+            "adaptive" => Ok(Self::Adaptive),
+            _ => Err(format!("unknown mode: {s}")),
+        }
+    }
+}
+
+impl CellMode {
+    // Pixels sampled per cell, as (columns, rows)
+    pub(crate) fn subcells(self) -> (u32, u32) {
+        match self {
+            CellMode::Glyphs | CellMode::Emoji => (1, 1),
+            CellMode::HalfBlock => (1, 2),
+            CellMode::Braille | CellMode::Octant | CellMode::Adaptive => (2, 4),
+        }
+    }
+}
+
+// Braille dot bits indexed by [row][column] of the 2x4 dot grid
+const BRAILLE_BITS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+const MAX_SUBCELLS: usize = 8;
+
+// Brightness variance (0..1 levels) of a cell's sub-cells above which it's drawn as
+// braille in adaptive mode; a hard black and white edge is 0.25
+const DETAIL_VARIANCE: f32 = 0.01;
+
+// First character of the Unicode 16 octant block, which skips the patterns that
+// were already encoded as other block elements
+const OCTANT_BASE: u32 = 0x1cd00;
+
+// Those patterns by mask (bit n for octant n + 1, numbered row by row), sorted
+const OCTANTS_ELSEWHERE: [(u8, char); 26] = [
+    (0x00, ' '),
+    (0x01, '\u{1cea8}'),
+    (0x02, '\u{1ceab}'),
+    (0x03, '\u{1fb82}'),
+    (0x05, '▘'),
+    (0x0a, '▝'),
+    (0x0f, '▀'),
+    (0x14, '\u{1fbe6}'),
+    (0x28, '\u{1fbe7}'),
+    (0x3f, '\u{1fb85}'),
+    (0x40, '\u{1cea3}'),
+    (0x50, '▖'),
+    (0x55, '▌'),
+    (0x5a, '▞'),
+    (0x5f, '▛'),
+    (0x80, '\u{1cea0}'),
+    (0xa0, '▗'),
+    (0xa5, '▚'),
+    (0xaa, '▐'),
+    (0xaf, '▜'),
+    (0xc0, '▂'),
+    (0xf0, '▄'),
+    (0xf5, '▙'),
+    (0xfa, '▟'),
+    (0xfc, '▆'),
+    (0xff, '█'),
+];
+
+// Half-block glyphs indexed by (top lit) | (bottom lit) << 1
+const HALF_BLOCKS: [char; 4] = [' ', '▀', '▄', '█'];
+
+// Converts an image resized to `dimensions` x the mode's sub-cell grid, lighting
+// every sub-cell whose brightness clears the midpoint. A cell's color is the average
+// of its lit sub-cells, which are the only ones drawn in the foreground color, so
+// colored braille keeps the dots' hue instead of dimming it with the unlit ones.
+// Adaptive mode draws only detailed cells as braille, split at their own mean so the
+// edge shows, and flat ones as charset glyphs, which don't add a dotted texture
+pub(crate) fn convert_subcells(
+    img: &DynamicImage,
+    dimensions: (u32, u32),
+    options: &ConversionOptions,
+) -> AsciiImage {
+    let rgba = img.to_rgba32f();
+    let (sub_w, sub_h) = options.mode.subcells();
+    let count = (sub_w * sub_h) as usize;
+    let (wr, wg, wb) = options.luma.weights();
+    let alpha_threshold = options.alpha_threshold.map(|t| t.clamp(0.0, 1.0));
+    let charset = Charset::parse(options.charset);
+    let mode = match options.mode {
+        CellMode::Octant if !octants_supported(options.font) => CellMode::Braille,
+        mode => mode,
+    };
+
+    let mut ascii_img = AsciiImage::create_empty(dimensions);
+    let mut cell_levels = [0.0; MAX_SUBCELLS];
+    let mut cell_pixels = [Rgba([0.0; 4]); MAX_SUBCELLS];
+    for cy in 0..dimensions.1 {
+        for cx in 0..dimensions.0 {
+            for sy in 0..sub_h {
+                for sx in 0..sub_w {
+                    let i = (sy * sub_w + sx) as usize;
+                    let pixel = *rgba.get_pixel(cx * sub_w + sx, cy * sub_h + sy);
+                    let luminance = wr * pixel[0] + wg * pixel[1] + wb * pixel[2];
+                    cell_levels[i] = options.adjust_brightness(luminance);
+                    cell_pixels[i] = pixel;
+                }
+            }
+            let (levels, pixels) = (&cell_levels[..count], &cell_pixels[..count]);
+
+            let mean = levels.iter().sum::<f32>() / count as f32;
+            let variance = levels.iter().map(|l| (l - mean).powi(2)).sum::<f32>() / count as f32;
+            let flat = options.mode == CellMode::Adaptive && variance < DETAIL_VARIANCE;
+            let threshold = if options.mode == CellMode::Adaptive {
+                mean
+            } else {
+                0.5
+            };
+            let mut lit = 0;
+            for (i, level) in levels.iter().enumerate() {
+                let x = cx * sub_w + i as u32 % sub_w;
+                let y = cy * sub_h + i as u32 / sub_w;
+                // Dithering is applied at sub-cell resolution, two levels per dot
+                let offset = options.dither_offset(x, y, 2);
+                if !flat && level + offset >= threshold {
+                    lit |= 1 << i;
+                }
+            }
+
+            let alpha = pixels.iter().map(|p| p[3]).sum::<f32>();
+            let transparent =
+                alpha_threshold.is_some_and(|threshold| alpha / (count as f32) < threshold);
+            ascii_img.transparent[cy as usize][cx as usize] = transparent;
+            // Blank and flat cells keep the whole cell's average
+            let (color_sum, inked) = (0..count).filter(|i| lit == 0 || lit & (1 << i) != 0).fold(
+                ([0.0; 3], 0),
+                |(mut sum, n), i| {
+                    for c in 0..3 {
+                        sum[c] += pixels[i][c];
+                    }
+                    (sum, n + 1)
+                },
+            );
+            ascii_img.colors[cy as usize][cx as usize] =
+                Rgb(color_sum.map(|c| quantize(c / inked as f32)));
+            // Adaptive cells span the charset's width whether drawn as braille or not
+            let pad = |glyph: char| match options.mode {
+                CellMode::Adaptive => charset.pad(&glyph.to_string()),
+                _ => glyph.to_string(),
+            };
+            ascii_img.data[cy as usize][cx as usize] = if transparent {
+                pad(options.transparent_char)
+            } else if flat {
+                let offset = options.dither_offset(cx, cy, charset.glyphs().len());
+                charset.glyph_for(mean + offset).to_string()
+            } else {
+                pad(subcell_glyph(mode, sub_w, lit))
+            };
+        }
+    }
+    ascii_img
+}
+
+// `lit` has a bit per sub-cell in row major order
+fn subcell_glyph(mode: CellMode, sub_w: u32, lit: u32) -> char {
+    match mode {
+        CellMode::HalfBlock => HALF_BLOCKS[lit as usize],
+        CellMode::Octant => {
+            let mask = lit as u8;
+            match OCTANTS_ELSEWHERE.binary_search_by_key(&mask, |&(m, _)| m) {
+                Ok(i) => OCTANTS_ELSEWHERE[i].1,
+                // The index is how many patterns below this one the block skips
+                Err(skipped) => {
+                    char::from_u32(OCTANT_BASE + mask as u32 - skipped as u32).unwrap_or(' ')
+                }
+            }
+        }
+        _ => {
+            let dots = (0..8)
+                .filter(|i| lit & (1 << i) != 0)
+                .map(|i| BRAILLE_BITS[(i / sub_w) as usize][(i % sub_w) as usize])
+                .sum::<u32>();
+            char::from_u32(0x2800 + dots).unwrap_or(' ')
+        }
+    }
+}
+
+// Few fonts have octants yet. Terminals can't be asked, but when rendering with a
+// font it can be checked, falling back to braille's matching 2x4 grid
+pub(crate) fn octants_supported(font: Option<&[u8]>) -> bool {
+    let font = font.and_then(|data| FontRef::try_from_slice(data).ok());
+    !font.is_some_and(|font| font.glyph_id(char::from_u32(OCTANT_BASE).unwrap_or(' ')).0 == 0)
+}
@@ -0,0 +1,108 @@
+use std::sync::{Arc, OnceLock};
+
+use image::{DynamicImage, RgbaImage};
+use resvg::{tiny_skia, usvg};
+
+use crate::{output_dimensions, ConversionOptions, DecodeLimits, ProgramError};
+
+// How far into the file the root element is looked for, past XML declarations,
+// comments and doctypes
+const SNIFF_BYTES: usize = 4096;
+
+pub(crate) fn is_svg_mime(mime_type: &str) -> bool {
+    mime_type.eq_ignore_ascii_case("image/svg+xml")
+}
+
+// Markup with an <svg> element near its start
+pub(crate) fn is_svg(bytes: &[u8]) -> bool {
+    let head = &bytes[..bytes.len().min(SNIFF_BYTES)];
+    let head = head.strip_prefix(b"\xef\xbb\xbf").unwrap_or(head);
+    let markup = head
+        .iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_some_and(|&b| b == b'<');
+    markup && head.windows(4).any(|w| w == b"<svg")
+}
+
+// Renders the SVG at the resolution the conversion samples (the output cells times
+// their sub-cells), so vector art stays crisp instead of being resized from whatever
+// size it declares
+pub(crate) fn rasterize(
+    bytes: &[u8],
+    options: &ConversionOptions,
+    limits: DecodeLimits,
+) -> Result<DynamicImage, ProgramError> {
+    let tree = parse(bytes)?;
+    let size = tree.size();
+    let scale = scale_for((size.width(), size.height()), options);
+    render_tree(&tree, scale, limits)
+}
+
+// Width and height the SVG declares, in user units
+pub(crate) fn declared_size(bytes: &[u8]) -> Result<(f32, f32), ProgramError> {
+    let size = parse(bytes)?.size();
+    Ok((size.width(), size.height()))
+}
+
+// Scale covering the sampled grid in both directions, which only differ by the
+// cells' aspect ratio
+pub(crate) fn scale_for((width, height): (f32, f32), options: &ConversionOptions) -> f32 {
+    let declared = ((width.ceil() as u32).max(1), (height.ceil() as u32).max(1));
+    let (columns, rows) = output_dimensions(declared, options);
+    let (sub_w, sub_h) = options.subcell_size();
+    ((columns * sub_w) as f32 / width).max((rows * sub_h) as f32 / height)
+}
+
+pub(crate) fn render(
+    bytes: &[u8],
+    scale: f32,
+    limits: DecodeLimits,
+) -> Result<DynamicImage, ProgramError> {
+    render_tree(&parse(bytes)?, scale, limits)
+}
+
+fn parse(bytes: &[u8]) -> Result<usvg::Tree, ProgramError> {
+    // Scanning the system fonts takes a while, so it's done once per process
+    static FONTS: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+    let fontdb = FONTS.get_or_init(|| {
+        let mut fontdb = usvg::fontdb::Database::new();
+        fontdb.load_system_fonts();
+        Arc::new(fontdb)
+    });
+    let svg_options = usvg::Options {
+        fontdb: Arc::clone(fontdb),
+        ..usvg::Options::default()
+    };
+    usvg::Tree::from_data(bytes, &svg_options).map_err(|_| ProgramError::FailedToDecodeInput)
+}
+
+fn render_tree(
+    tree: &usvg::Tree,
+    scale: f32,
+    limits: DecodeLimits,
+) -> Result<DynamicImage, ProgramError> {
+    let size = tree.size();
+    let (w, h) = (
+        ((size.width() * scale).ceil() as u32).max(1),
+        ((size.height() * scale).ceil() as u32).max(1),
+    );
+    limits.check_dimensions(w, h)?;
+
+    let mut pixmap = tiny_skia::Pixmap::new(w, h).ok_or(ProgramError::ImageTooLarge)?;
+    resvg::render(
+        tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    // Pixmaps are premultiplied
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    let img = RgbaImage::from_raw(w, h, pixels).ok_or(ProgramError::FailedToDecodeInput)?;
+    Ok(DynamicImage::ImageRgba8(img))
+}
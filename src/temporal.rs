@@ -0,0 +1,87 @@
+use std::sync::OnceLock;
+
+use image::Rgb;
+
+use crate::{AsciiImage, ConversionOptions, Dither};
+
+const MASK_SIZE: usize = 16;
+const MASK_SIGMA: f32 = 1.5;
+
+// Golden ratio conjugate; adding it per frame spreads each cell's thresholds
+// evenly over time
+const FRAME_STEP: f32 = 0.618_034;
+
+// Largest color change (per channel, 0..255) for which a cell keeps its previous glyph
+const PERSISTENCE: u8 = 12;
+
+// Threshold in 0..1 from the blue-noise mask, rotated for every frame
+pub(crate) fn threshold(x: u32, y: u32, frame: u32) -> f32 {
+    let index = (y as usize % MASK_SIZE) * MASK_SIZE + x as usize % MASK_SIZE;
+    (blue_noise()[index] + frame as f32 * FRAME_STEP).fract()
+}
+
+// Ranks the mask cells by repeatedly filling the largest void, i.e. the cell with
+// the least Gaussian energy from the cells filled so far (on a torus, so the mask tiles)
+fn blue_noise() -> &'static [f32; MASK_SIZE * MASK_SIZE] {
+    static MASK: OnceLock<[f32; MASK_SIZE * MASK_SIZE]> = OnceLock::new();
+    MASK.get_or_init(|| {
+        const CELLS: usize = MASK_SIZE * MASK_SIZE;
+        let mut mask = [0.0; CELLS];
+        let mut filled = [false; CELLS];
+        let mut energy = [0.0f32; CELLS];
+        for rank in 0..CELLS {
+            let void = (0..CELLS)
+                .filter(|&i| !filled[i])
+                .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+                .unwrap_or(0);
+            filled[void] = true;
+            mask[void] = (rank as f32 + 0.5) / CELLS as f32;
+            for (i, e) in energy.iter_mut().enumerate() {
+                let dx = toroidal_distance(i % MASK_SIZE, void % MASK_SIZE);
+                let dy = toroidal_distance(i / MASK_SIZE, void / MASK_SIZE);
+                *e += (-(dx * dx + dy * dy) / (2.0 * MASK_SIGMA * MASK_SIGMA)).exp();
+            }
+        }
+        mask
+    })
+}
+
+fn toroidal_distance(a: usize, b: usize) -> f32 {
+    let d = a.abs_diff(b);
+    d.min(MASK_SIZE - d) as f32
+}
+
+// Keeps a cell's glyph from the previous frame while its color stays close to the
+// color it had when the glyph last changed, so static areas don't crawl as the
+// mask rotates while moving ones still update
+pub(crate) fn persist(frames: &mut [AsciiImage], options: &ConversionOptions) {
+    if options.dither != Dither::Temporal {
+        return;
+    }
+    let Some(first) = frames.first() else {
+        return;
+    };
+    let mut anchors = first.colors.clone();
+    for i in 1..frames.len() {
+        let (done, rest) = frames.split_at_mut(i);
+        let (previous, frame) = (&done[i - 1], &mut rest[0]);
+        if frame.dimensions != previous.dimensions {
+            anchors = frame.colors.clone();
+            continue;
+        }
+        for (y, row) in anchors.iter_mut().enumerate() {
+            for (x, anchor) in row.iter_mut().enumerate() {
+                if color_distance(frame.colors[y][x], *anchor) <= PERSISTENCE {
+                    frame.data[y][x].clone_from(&previous.data[y][x]);
+                    frame.transparent[y][x] = previous.transparent[y][x];
+                } else {
+                    *anchor = frame.colors[y][x];
+                }
+            }
+        }
+    }
+}
+
+fn color_distance(a: Rgb<u8>, b: Rgb<u8>) -> u8 {
+    (0..3).map(|c| a[c].abs_diff(b[c])).max().unwrap_or(0)
+}
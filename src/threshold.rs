@@ -0,0 +1,62 @@
+use std::str::FromStr;
+
+use crate::tones::LEVELS;
+
+// Brightness cutoff of the 1-bit mode: pixels below it get the charset's first glyph
+// (or a blank sub-cell), the rest its last one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Threshold {
+    // 0..1
+    Cutoff(f32),
+    // Otsu's method, the cutoff best separating the image's dark and light pixels
+    Otsu,
+}
+
+impl FromStr for Threshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" | "otsu" => Ok(Self::Otsu),
+            _ => s
+                .parse()
+                .ok()
+                .filter(|cutoff| (0.0..=1.0).contains(cutoff))
+                .map(Self::Cutoff)
+                .ok_or_else(|| format!("invalid threshold: {s} (expected 0..1 or auto)")),
+        }
+    }
+}
+
+// The cutoff maximizing the variance between the two classes of a luminance histogram
+pub(crate) fn otsu(histogram: &[u64; LEVELS]) -> f32 {
+    let total: u64 = histogram.iter().sum();
+    let sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(level, &count)| level as f64 * count as f64)
+        .sum();
+
+    let (mut dark_count, mut dark_sum) = (0u64, 0.0);
+    let (mut best_level, mut best_variance) = (127, -1.0);
+    for (level, &count) in histogram.iter().enumerate() {
+        dark_count += count;
+        dark_sum += level as f64 * count as f64;
+        let light_count = total - dark_count;
+        if dark_count == 0 {
+            continue;
+        }
+        if light_count == 0 {
+            break;
+        }
+        let dark_mean = dark_sum / dark_count as f64;
+        let light_mean = (sum - dark_sum) / light_count as f64;
+        let variance = dark_count as f64 * light_count as f64 * (dark_mean - light_mean).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best_level = level;
+        }
+    }
+    // Levels up to the best one are dark
+    (best_level as f32 + 0.5) / (LEVELS - 1) as f32
+}
@@ -0,0 +1,93 @@
+use image::{DynamicImage, GenericImageView, Rgba};
+
+use crate::{Luma, ProgramError};
+
+pub(crate) const LEVELS: usize = 256;
+
+// Luminance distribution of a reference image that inputs are histogram-matched to,
+// so differently exposed photos come out with the same tonality
+#[derive(Debug, Clone)]
+pub struct ToneReference {
+    cdf: [f32; LEVELS],
+}
+
+impl ToneReference {
+    pub fn from_image(img: &DynamicImage, luma: Luma) -> Self {
+        Self {
+            cdf: luminance_cdf(img, luma),
+        }
+    }
+
+    // Maps every luminance level of `img` to the reference level at the same
+    // cumulative frequency
+    pub(crate) fn tone_map(&self, img: &DynamicImage, luma: Luma) -> ToneMap {
+        let source_cdf = luminance_cdf(img, luma);
+        let mut levels = [0; LEVELS];
+        let mut target = 0;
+        for (level, &frequency) in source_cdf.iter().enumerate() {
+            while target < LEVELS - 1 && self.cdf[target] < frequency {
+                target += 1;
+            }
+            levels[level] = target as u8;
+        }
+        ToneMap { levels, luma }
+    }
+}
+
+pub fn load_tone_reference(path: &str, luma: Luma) -> Result<ToneReference, ProgramError> {
+    let img = image::open(path).map_err(|_| ProgramError::FailedToReadToneReference)?;
+    Ok(ToneReference::from_image(&img, luma))
+}
+
+pub(crate) struct ToneMap {
+    levels: [u8; LEVELS],
+    luma: Luma,
+}
+
+impl ToneMap {
+    // Scales each pixel's color to the mapped luminance, keeping hue and alpha
+    pub(crate) fn apply(&self, img: &DynamicImage) -> DynamicImage {
+        let (wr, wg, wb) = self.luma.weights();
+        let mut rgba = img.to_rgba8();
+        for pixel in rgba.pixels_mut() {
+            let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|c| c as f32);
+            let luminance = wr * r + wg * g + wb * b;
+            let target = self.levels[luminance.round() as usize] as f32;
+            for c in 0..3 {
+                pixel[c] = if luminance > 0.0 {
+                    (pixel[c] as f32 * target / luminance).round().min(255.0) as u8
+                } else {
+                    target as u8
+                };
+            }
+        }
+        DynamicImage::ImageRgba8(rgba)
+    }
+}
+
+fn luminance_cdf(img: &DynamicImage, luma: Luma) -> [f32; LEVELS] {
+    let histogram = luminance_histogram(img, luma);
+    let total = histogram.iter().sum::<u64>().max(1) as f32;
+    let mut cdf = [0.0; LEVELS];
+    let mut running = 0;
+    for (level, count) in histogram.iter().enumerate() {
+        running += count;
+        cdf[level] = running as f32 / total;
+    }
+    cdf
+}
+
+// Pixel count of every luminance level. Fully transparent pixels don't count, they
+// end up as background anyway
+pub(crate) fn luminance_histogram(
+    img: &impl GenericImageView<Pixel = Rgba<u8>>,
+    luma: Luma,
+) -> [u64; LEVELS] {
+    let (wr, wg, wb) = luma.weights();
+    let mut histogram = [0; LEVELS];
+    for (_, _, pixel) in img.pixels().filter(|(_, _, pixel)| pixel[3] > 0) {
+        let luminance = wr * pixel[0] as f32 + wg * pixel[1] as f32 + wb * pixel[2] as f32;
+        histogram[(luminance.round() as usize).min(LEVELS - 1)] += 1;
+    }
+    histogram
+}
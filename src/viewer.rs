@@ -0,0 +1,406 @@
+use image::DynamicImage;
+
+use crate::{ConversionOptions, ProgramError};
+
+// Shows the image full screen, panning with the arrow keys and zooming with +/-
+// (0 resets, q or Esc quits). Every view is converted from the full resolution image.
+// With `record`, the zoom and pan steps are saved there as a session script
+pub fn run_viewer(
+    img: &DynamicImage,
+    options: &ConversionOptions,
+    record: Option<&str>,
+) -> Result<(), ProgramError> {
+    #[cfg(feature = "viewer")]
+    {
+        tui::view(img, options, record)
+    }
+    #[cfg(not(feature = "viewer"))]
+    {
+        let _ = (img, options, record);
+        Err(ProgramError::ViewerUnavailable)
+    }
+}
+
+// Shows the whole image while keys adjust the settings (c/C charset preset, a/A aspect
+// ratio, [/] contrast, g/G gamma, d dithering, m colors), then prints them as flags
+// once q, Esc or Enter quits. With `record`, every adjustment is saved there as a
+// session script
+pub fn run_tuner(
+    img: &DynamicImage,
+    options: &ConversionOptions,
+    record: Option<&str>,
+) -> Result<(), ProgramError> {
+    #[cfg(feature = "viewer")]
+    {
+        tui::tune(img, options, record)
+    }
+    #[cfg(not(feature = "viewer"))]
+    {
+        let _ = (img, options, record);
+        Err(ProgramError::ViewerUnavailable)
+    }
+}
+
+#[cfg(feature = "viewer")]
+mod tui {
+    use std::io::{self, Write};
+
+    use crossterm::{
+        cursor,
+        event::{self, Event, KeyCode, KeyEventKind},
+        execute, queue, terminal,
+    };
+    use image::{DynamicImage, GenericImageView};
+
+    use crate::{
+        convert_region, ArtOutput, ColorMode, ConversionOptions, Dither, OutputFormat,
+        ProgramError, Rect, CHARSET_PRESETS,
+    };
+
+    const ZOOM_STEP: f32 = 1.25;
+    const MAX_ZOOM: f32 = 64.0;
+
+    // Share of the viewport an arrow key pans by
+    const PAN_STEP: f32 = 0.125;
+
+    const ASPECT_STEP: f32 = 0.05;
+    const TONE_STEP: f32 = 0.1;
+    // Temporal dithering only makes sense for animations
+    const DITHERS: [Dither; 2] = [Dither::None, Dither::Ordered];
+    const COLORS: [ColorMode; 4] = [
+        ColorMode::None,
+        ColorMode::Ansi16,
+        ColorMode::Ansi256,
+        ColorMode::TrueColor,
+    ];
+
+    pub(super) fn view(
+        img: &DynamicImage,
+        options: &ConversionOptions,
+        record: Option<&str>,
+    ) -> Result<(), ProgramError> {
+        let options = ConversionOptions {
+            format: OutputFormat::Text,
+            ..options.clone()
+        };
+        let _terminal = RawTerminal::enter().map_err(|_| ProgramError::FailedToWriteToOutput)?;
+        let (w, h) = img.dimensions();
+        let (mut center, mut zoom) = ((w as f32 / 2.0, h as f32 / 2.0), 1.0);
+        let mut stdout = io::stdout().lock();
+        let mut redraw = true;
+        let mut steps = Vec::new();
+        let mut recorded = (0.0, 0.0, 1.0, 1.0);
+        loop {
+            let (columns, rows) = terminal_size()?;
+            // Zoom 1 fits the whole image
+            let pixels = fit((w, h), columns, rows, options.symbol_aspect_ratio) / zoom;
+            let extent = (
+                columns * pixels,
+                rows * pixels / options.symbol_aspect_ratio,
+            );
+            center = (
+                clamp_center(center.0, extent.0, w),
+                clamp_center(center.1, extent.1, h),
+            );
+
+            // Steps record the region shown, clipped to the image along axes the
+            // viewport is larger than it, so they replay exactly what was on screen
+            let (x, width) = shown(center.0, extent.0, w);
+            let (y, height) = shown(center.1, extent.1, h);
+            let view = (x, y, width, height);
+            if view != recorded {
+                steps.push(format!("region {x:.4} {y:.4} {width:.4} {height:.4}"));
+                recorded = view;
+            }
+
+            if redraw {
+                let region = Rect {
+                    x: (center.0 - extent.0 / 2.0).max(0.0) as u32,
+                    y: (center.1 - extent.1 / 2.0).max(0.0) as u32,
+                    width: (extent.0 as u32).max(1),
+                    height: (extent.1 as u32).max(1),
+                };
+                let output = convert_region(img, region, 1.0 / pixels, &options)?;
+                draw(&mut stdout, &output, rows as usize)
+                    .and_then(|_| stdout.flush())
+                    .map_err(|_| ProgramError::FailedToWriteToOutput)?;
+            }
+
+            let event = event::read().map_err(|_| ProgramError::FailedToWriteToOutput)?;
+            redraw = match event {
+                Event::Key(key) if key.kind != KeyEventKind::Release => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => break,
+                    KeyCode::Left => {
+                        center.0 -= extent.0 * PAN_STEP;
+                        true
+                    }
+                    KeyCode::Right => {
+                        center.0 += extent.0 * PAN_STEP;
+                        true
+                    }
+                    KeyCode::Up => {
+                        center.1 -= extent.1 * PAN_STEP;
+                        true
+                    }
+                    KeyCode::Down => {
+                        center.1 += extent.1 * PAN_STEP;
+                        true
+                    }
+                    KeyCode::Char('+') | KeyCode::Char('=') => {
+                        zoom = (zoom * ZOOM_STEP).min(MAX_ZOOM);
+                        true
+                    }
+                    KeyCode::Char('-') => {
+                        zoom = (zoom / ZOOM_STEP).max(1.0);
+                        true
+                    }
+                    KeyCode::Char('0') => {
+                        zoom = 1.0;
+                        true
+                    }
+                    _ => false,
+                },
+                Event::Resize(..) => true,
+                _ => false,
+            };
+        }
+        match record {
+            Some(path) => save_session(path, &steps),
+            None => Ok(()),
+        }
+    }
+
+    pub(super) fn tune(
+        img: &DynamicImage,
+        options: &ConversionOptions,
+        record: Option<&str>,
+    ) -> Result<(), ProgramError> {
+        let mut options = ConversionOptions {
+            format: OutputFormat::Text,
+            ..options.clone()
+        };
+        let (w, h) = img.dimensions();
+        let mut steps = Vec::new();
+        {
+            let _terminal =
+                RawTerminal::enter().map_err(|_| ProgramError::FailedToWriteToOutput)?;
+            let mut stdout = io::stdout().lock();
+            let mut redraw = true;
+            loop {
+                if redraw {
+                    let (columns, rows) = terminal_size()?;
+                    // The last row shows the settings
+                    let rows = (rows - 1.0).max(1.0);
+                    let pixels = fit((w, h), columns, rows, options.symbol_aspect_ratio);
+                    let region = Rect {
+                        x: 0,
+                        y: 0,
+                        width: w,
+                        height: h,
+                    };
+                    let output = convert_region(img, region, 1.0 / pixels, &options)?;
+                    let status: String = flags(&options).chars().take(columns as usize).collect();
+                    draw(&mut stdout, &output, rows as usize)
+                        .and_then(|_| queue!(stdout, cursor::MoveTo(0, rows as u16)))
+                        .and_then(|_| write!(stdout, "\x1b[7m{status}\x1b[0m"))
+                        .and_then(|_| stdout.flush())
+                        .map_err(|_| ProgramError::FailedToWriteToOutput)?;
+                }
+
+                let event = event::read().map_err(|_| ProgramError::FailedToWriteToOutput)?;
+                let Event::Key(key) = event else {
+                    redraw = matches!(event, Event::Resize(..));
+                    continue;
+                };
+                if key.kind == KeyEventKind::Release {
+                    redraw = false;
+                    continue;
+                }
+                redraw = true;
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => break,
+                    KeyCode::Char('c') => options.charset = cycle_preset(options.charset, 1),
+                    KeyCode::Char('C') => options.charset = cycle_preset(options.charset, -1),
+                    KeyCode::Char('a') => {
+                        options.symbol_aspect_ratio =
+                            adjust(options.symbol_aspect_ratio, -ASPECT_STEP, ASPECT_STEP)
+                    }
+                    KeyCode::Char('A') => {
+                        options.symbol_aspect_ratio =
+                            adjust(options.symbol_aspect_ratio, ASPECT_STEP, ASPECT_STEP)
+                    }
+                    KeyCode::Char('[') => {
+                        options.contrast = adjust(options.contrast, -TONE_STEP, 0.0)
+                    }
+                    KeyCode::Char(']') => {
+                        options.contrast = adjust(options.contrast, TONE_STEP, 0.0)
+                    }
+                    KeyCode::Char('g') => {
+                        options.gamma = adjust(options.gamma, -TONE_STEP, TONE_STEP)
+                    }
+                    KeyCode::Char('G') => {
+                        options.gamma = adjust(options.gamma, TONE_STEP, TONE_STEP)
+                    }
+                    KeyCode::Char('d') => options.dither = cycle(&DITHERS, options.dither, 1),
+                    KeyCode::Char('m') => options.color = cycle(&COLORS, options.color, 1),
+                    _ => redraw = false,
+                }
+                if redraw {
+                    steps.extend(step(key.code, &options));
+                }
+            }
+        }
+        // Printed once the terminal is restored, so it stays on screen
+        println!("{}", flags(&options));
+        match record {
+            Some(path) => save_session(path, &steps),
+            None => Ok(()),
+        }
+    }
+
+    fn terminal_size() -> Result<(f32, f32), ProgramError> {
+        let (columns, rows) = terminal::size().map_err(|_| ProgramError::FailedToWriteToOutput)?;
+        Ok((columns.max(1) as f32, rows.max(1) as f32))
+    }
+
+    // Source pixels per column that fit the whole image in the terminal
+    fn fit((w, h): (u32, u32), columns: f32, rows: f32, symbol_aspect_ratio: f32) -> f32 {
+        (w as f32 / columns).max(h as f32 * symbol_aspect_ratio / rows)
+    }
+
+    // Rounded to hundredths so repeated steps don't accumulate float error
+    fn adjust(value: f32, step: f32, min: f32) -> f32 {
+        (((value + step) * 100.0).round() / 100.0).max(min)
+    }
+
+    // A custom charset goes to the first preset
+    fn cycle_preset(charset: &str, step: isize) -> &str {
+        let charsets: Vec<&str> = CHARSET_PRESETS
+            .iter()
+            .map(|(_, charset)| *charset)
+            .collect();
+        cycle(&charsets, charset, step)
+    }
+
+    // Items not in the list go to the first one
+    fn cycle<T: Copy + PartialEq>(items: &[T], current: T, step: isize) -> T {
+        let index = items
+            .iter()
+            .position(|item| *item == current)
+            .map_or(0, |i| {
+                (i as isize + step).rem_euclid(items.len() as isize) as usize
+            });
+        items[index]
+    }
+
+    // The tuned settings as command line flags
+    fn flags(options: &ConversionOptions) -> String {
+        let charset = match preset_name(options.charset) {
+            Some(name) => format!("--preset {name}"),
+            None => format!("--charset '{}'", options.charset.replace('\'', r"'\''")),
+        };
+        let (dither, color) = (dither_name(options.dither), color_name(options.color));
+        format!(
+            "{charset} --symbol-aspect-ratio {:.2} --contrast {:.2} --gamma {:.2} \
+             --dither {dither} --color {color}",
+            options.symbol_aspect_ratio, options.contrast, options.gamma
+        )
+    }
+
+    // The session script line for the setting a key changed
+    fn step(code: KeyCode, options: &ConversionOptions) -> Option<String> {
+        Some(match code {
+            KeyCode::Char('c' | 'C') => match preset_name(options.charset) {
+                Some(name) => format!("preset {name}"),
+                None => format!("charset {}", options.charset),
+            },
+            KeyCode::Char('a' | 'A') => {
+                format!("symbol-aspect-ratio {:.2}", options.symbol_aspect_ratio)
+            }
+            KeyCode::Char('[' | ']') => format!("contrast {:.2}", options.contrast),
+            KeyCode::Char('g' | 'G') => format!("gamma {:.2}", options.gamma),
+            KeyCode::Char('d') => format!("dither {}", dither_name(options.dither)),
+            KeyCode::Char('m') => format!("color {}", color_name(options.color)),
+            _ => return None,
+        })
+    }
+
+    fn preset_name(charset: &str) -> Option<&'static str> {
+        CHARSET_PRESETS
+            .iter()
+            .find(|(_, preset)| *preset == charset)
+            .map(|(name, _)| *name)
+    }
+
+    fn dither_name(dither: Dither) -> &'static str {
+        match dither {
+            Dither::None => "none",
+            Dither::Ordered => "ordered",
+            Dither::Temporal => "temporal",
+        }
+    }
+
+    fn color_name(color: ColorMode) -> &'static str {
+        match color {
+            ColorMode::None => "none",
+            ColorMode::Ansi16 => "16",
+            ColorMode::Ansi256 => "256",
+            ColorMode::TrueColor => "truecolor",
+        }
+    }
+
+    fn save_session(path: &str, steps: &[String]) -> Result<(), ProgramError> {
+        let mut script = String::from("# image2unicodeart session, see --apply-session\n");
+        for step in steps {
+            script.push_str(step);
+            script.push('\n');
+        }
+        std::fs::write(path, script).map_err(|_| ProgramError::FailedToWriteToOutput)
+    }
+
+    // Start and length of the viewport along an axis, as shares of the image's size
+    fn shown(center: f32, extent: f32, size: u32) -> (f32, f32) {
+        let size = size as f32;
+        let start = (center - extent / 2.0).max(0.0);
+        (start / size, extent.min(size - start) / size)
+    }
+
+    // Keeps the viewport inside the image, centering it along axes where it's larger
+    fn clamp_center(center: f32, extent: f32, size: u32) -> f32 {
+        let size = size as f32;
+        if extent >= size {
+            size / 2.0
+        } else {
+            center.clamp(extent / 2.0, size - extent / 2.0)
+        }
+    }
+
+    fn draw(out: &mut impl Write, output: &ArtOutput, rows: usize) -> io::Result<()> {
+        queue!(out, terminal::Clear(terminal::ClearType::All))?;
+        // Rounding can yield a row more than fits, which would scroll the screen
+        let text = output.as_text().unwrap_or_default();
+        for (row, line) in text.lines().take(rows).enumerate() {
+            queue!(out, cursor::MoveTo(0, row as u16))?;
+            out.write_all(line.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    // Raw mode on the alternate screen, restored even when the viewer fails
+    struct RawTerminal;
+
+    impl RawTerminal {
+        fn enter() -> io::Result<Self> {
+            terminal::enable_raw_mode()?;
+            execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+            Ok(Self)
+        }
+    }
+
+    impl Drop for RawTerminal {
+        fn drop(&mut self) {
+            let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+}
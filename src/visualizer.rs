@@ -0,0 +1,213 @@
+use std::str::FromStr;
+
+use crate::{ConversionOptions, ProgramError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisualizerMode {
+    Spectrum,
+    Waveform,
+}
+
+impl FromStr for VisualizerMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spectrum" => Ok(Self::Spectrum),
+            "waveform" => Ok(Self::Waveform),
+            _ => Err(format!("unknown visualizer mode: {s}")),
+        }
+    }
+}
+
+// Captures the default input device and renders it through the converter until interrupted
+pub fn run_visualizer(
+    mode: VisualizerMode,
+    options: &ConversionOptions,
+) -> Result<(), ProgramError> {
+    #[cfg(feature = "visualizer")]
+    {
+        capture::run(mode, options)
+    }
+    #[cfg(not(feature = "visualizer"))]
+    {
+        let _ = (mode, options);
+        Err(ProgramError::VisualizerUnavailable)
+    }
+}
+
+#[cfg(feature = "visualizer")]
+mod capture {
+    use std::{
+        collections::VecDeque,
+        f32::consts::PI,
+        io::{self, Write},
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use image::{DynamicImage, GrayImage, Luma};
+
+    use super::VisualizerMode;
+    use crate::{
+        animation::FRAMES_PER_SECOND, render, resize_and_convert, terminal_height, terminal_width,
+        ConversionOptions, ProgramError,
+    };
+
+    const WINDOW: usize = 2048;
+    const MIN_FREQUENCY: f32 = 40.0;
+    const MAX_FREQUENCY: f32 = 16000.0;
+    const DB_FLOOR: f32 = -60.0;
+
+    type Samples = Arc<Mutex<VecDeque<f32>>>;
+
+    pub(super) fn run(
+        mode: VisualizerMode,
+        options: &ConversionOptions,
+    ) -> Result<(), ProgramError> {
+        let host = cpal::default_host();
+        let device = host
+            .default_input_device()
+            .ok_or(ProgramError::AudioCaptureFailed)?;
+        let supported = device
+            .default_input_config()
+            .map_err(|_| ProgramError::AudioCaptureFailed)?;
+        let sample_rate = supported.sample_rate().0 as f32;
+        let channels = supported.channels() as usize;
+        let config = supported.config();
+
+        let samples: Samples = Arc::new(Mutex::new(VecDeque::with_capacity(WINDOW)));
+        let buffer = Arc::clone(&samples);
+        let stream = match supported.sample_format() {
+            cpal::SampleFormat::F32 => device.build_input_stream(
+                &config,
+                move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                    push_samples(&buffer, data.iter().copied(), channels)
+                },
+                |_| {},
+                None,
+            ),
+            cpal::SampleFormat::I16 => device.build_input_stream(
+                &config,
+                move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                    let data = data.iter().map(|s| *s as f32 / i16::MAX as f32);
+                    push_samples(&buffer, data, channels)
+                },
+                |_| {},
+                None,
+            ),
+            _ => return Err(ProgramError::AudioCaptureFailed),
+        }
+        .map_err(|_| ProgramError::AudioCaptureFailed)?;
+        stream
+            .play()
+            .map_err(|_| ProgramError::AudioCaptureFailed)?;
+
+        let width = options.output_width.unwrap_or_else(terminal_width).max(1);
+        // Wide glyphs fit fewer bars or samples into the terminal
+        let columns = (width / options.cell_width()).max(1);
+        let height = terminal_height().saturating_sub(1).max(1);
+        // Frames are drawn at the grid's size, so only errors matter, not warnings about
+        // upscaling them
+        options.validate((columns, height))?;
+        let renderer = render::renderer(options)?;
+        let frame_delay = Duration::from_secs(1) / FRAMES_PER_SECOND;
+        let mut stdout = io::stdout().lock();
+        let mut first = true;
+        loop {
+            let window: Vec<f32> = samples.lock().unwrap().iter().copied().collect();
+            let levels = match mode {
+                VisualizerMode::Spectrum => spectrum(&window, columns, sample_rate),
+                VisualizerMode::Waveform => waveform(&window, columns),
+            };
+            let img = match mode {
+                VisualizerMode::Spectrum => draw_bars(&levels, height),
+                VisualizerMode::Waveform => draw_wave(&levels, height),
+            };
+            let art =
+                resize_and_convert(&DynamicImage::ImageLuma8(img), (columns, height), options);
+
+            if !first {
+                let _ = write!(stdout, "\x1b[{height}A");
+            }
+            first = false;
+            let _ = stdout.write_all(&renderer.render(&art));
+            let _ = stdout.flush();
+            thread::sleep(frame_delay);
+        }
+    }
+
+    fn push_samples(buffer: &Samples, data: impl Iterator<Item = f32>, channels: usize) {
+        let mut buffer = buffer.lock().unwrap();
+        let data: Vec<f32> = data.collect();
+        // Downmix interleaved channels to mono
+        for frame in data.chunks(channels.max(1)) {
+            if buffer.len() == WINDOW {
+                buffer.pop_front();
+            }
+            buffer.push_back(frame.iter().sum::<f32>() / frame.len() as f32);
+        }
+    }
+
+    // Magnitudes of log-spaced frequency bands, one per column, mapped to 0..1 on a dB scale
+    fn spectrum(window: &[f32], columns: u32, sample_rate: f32) -> Vec<f32> {
+        let n = window.len();
+        if n == 0 {
+            return vec![0.0; columns as usize];
+        }
+        let windowed: Vec<f32> = window
+            .iter()
+            .enumerate()
+            .map(|(i, s)| s * 0.5 * (1.0 - (2.0 * PI * i as f32 / n as f32).cos()))
+            .collect();
+
+        let max_frequency = MAX_FREQUENCY.min(sample_rate / 2.0);
+        (0..columns)
+            .map(|column| {
+                let t = (column as f32 + 0.5) / columns as f32;
+                let frequency = MIN_FREQUENCY * (max_frequency / MIN_FREQUENCY).powf(t);
+                let omega = 2.0 * PI * frequency / sample_rate;
+                let (re, im) = windowed
+                    .iter()
+                    .enumerate()
+                    .fold((0.0, 0.0), |(re, im), (i, s)| {
+                        let phase = omega * i as f32;
+                        (re + s * phase.cos(), im - s * phase.sin())
+                    });
+                let magnitude = 4.0 * (re * re + im * im).sqrt() / n as f32;
+                let db = 20.0 * magnitude.max(1e-9).log10();
+                ((db - DB_FLOOR) / -DB_FLOOR).clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+
+    // The most recent samples resampled to one value per column, mapped to 0..1
+    fn waveform(window: &[f32], columns: u32) -> Vec<f32> {
+        (0..columns as usize)
+            .map(|column| {
+                let sample = if window.is_empty() {
+                    0.0
+                } else {
+                    window[column * window.len() / columns as usize]
+                };
+                ((sample + 1.0) / 2.0).clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+
+    fn draw_bars(levels: &[f32], height: u32) -> GrayImage {
+        GrayImage::from_fn(levels.len() as u32, height, |x, y| {
+            let fill = levels[x as usize] * height as f32 - (height - 1 - y) as f32;
+            Luma([(fill.clamp(0.0, 1.0) * u8::MAX as f32) as u8])
+        })
+    }
+
+    fn draw_wave(levels: &[f32], height: u32) -> GrayImage {
+        GrayImage::from_fn(levels.len() as u32, height, |x, y| {
+            let row = ((1.0 - levels[x as usize]) * (height - 1) as f32).round() as u32;
+            Luma([if row == y { u8::MAX } else { 0 }])
+        })
+    }
+}
@@ -0,0 +1,40 @@
+use std::str::FromStr;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{convert_bytes, CellMode, ColorMode, ConversionOptions, OutputFormat};
+
+// Converts encoded image bytes to text or markup for JavaScript callers, e.g.
+// convertBytes(bytes, 80, undefined, "html"). Unset options keep their defaults
+#[wasm_bindgen(js_name = convertBytes)]
+pub fn convert_bytes_js(
+    bytes: &[u8],
+    width: Option<u32>,
+    charset: Option<String>,
+    format: Option<String>,
+    mode: Option<String>,
+    color: Option<String>,
+) -> Result<String, JsError> {
+    let defaults = ConversionOptions::default();
+    let format: OutputFormat = parse_option(format)?.unwrap_or(defaults.format);
+    if format.is_binary() {
+        return Err(JsError::new("binary output formats aren't supported"));
+    }
+    let options = ConversionOptions {
+        output_width: width,
+        charset: charset.as_deref().unwrap_or(defaults.charset),
+        format,
+        mode: parse_option::<CellMode>(mode)?.unwrap_or(defaults.mode),
+        color: parse_option::<ColorMode>(color)?.unwrap_or(defaults.color),
+        ..defaults
+    };
+    let output =
+        convert_bytes(bytes, None, &options).map_err(|err| JsError::new(&format!("{err:?}")))?;
+    Ok(output.as_text().unwrap_or_default().to_string())
+}
+
+fn parse_option<T: FromStr<Err = String>>(value: Option<String>) -> Result<Option<T>, JsError> {
+    value
+        .map(|value| value.parse().map_err(|err: String| JsError::new(&err)))
+        .transpose()
+}
@@ -0,0 +1,63 @@
+use image::{imageops::FilterType, DynamicImage, GenericImageView, Rgba};
+
+use crate::{Focus, ProgramError};
+
+// Largest share of the image's width and height a watermark covers; bigger logos are
+// scaled down to it
+const MAX_SHARE: f32 = 0.25;
+
+// Gap between the watermark and the image's edges, as a share of its shorter side
+const MARGIN_SHARE: f32 = 0.02;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Watermark<'a> {
+    pub image: &'a DynamicImage,
+    // 0 (invisible) to 1 (as opaque as the watermark's own alpha)
+    pub opacity: f32,
+    pub position: Focus,
+}
+
+pub fn load_watermark(path: &str) -> Result<DynamicImage, ProgramError> {
+    image::open(path).map_err(|_| ProgramError::FailedToReadWatermark)
+}
+
+// Blends the watermark into the image at its position, before it's converted
+pub(crate) fn blend(img: DynamicImage, watermark: Option<&Watermark>) -> DynamicImage {
+    let Some(watermark) = watermark else {
+        return img;
+    };
+    let (w, h) = img.dimensions();
+    let (max_w, max_h) = ((w as f32 * MAX_SHARE) as u32, (h as f32 * MAX_SHARE) as u32);
+    if max_w == 0 || max_h == 0 {
+        return img;
+    }
+    let (logo_w, logo_h) = watermark.image.dimensions();
+    let logo = if logo_w > max_w || logo_h > max_h {
+        watermark.image.resize(max_w, max_h, FilterType::Triangle)
+    } else {
+        watermark.image.clone()
+    }
+    .to_rgba8();
+
+    let margin = (w.min(h) as f32 * MARGIN_SHARE) as u32;
+    let (ax, ay) = watermark.position.anchor();
+    let place = |size: u32, logo: u32, anchor: f32| {
+        let free = size - logo;
+        let margin = margin.min(free / 2);
+        margin + ((free - 2 * margin) as f32 * anchor).round() as u32
+    };
+    let (x0, y0) = (place(w, logo.width(), ax), place(h, logo.height(), ay));
+    let opacity = watermark.opacity.clamp(0.0, 1.0);
+
+    let mut rgba = img.to_rgba8();
+    for (x, y, &Rgba([r, g, b, a])) in logo.enumerate_pixels() {
+        let alpha = a as f32 / u8::MAX as f32 * opacity;
+        let Rgba(base) = rgba.get_pixel_mut(x0 + x, y0 + y);
+        for (channel, logo_channel) in base.iter_mut().zip([r, g, b]) {
+            *channel =
+                (*channel as f32 * (1.0 - alpha) + logo_channel as f32 * alpha).round() as u8;
+        }
+        base[3] = base[3].max((alpha * u8::MAX as f32).round() as u8);
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
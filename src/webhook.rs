@@ -0,0 +1,67 @@
+use crate::{Fetcher, ProgramError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookService {
+    Discord,
+    Slack,
+}
+
+impl WebhookService {
+    // Longest message the service accepts, in characters
+    fn message_limit(self) -> usize {
+        match self {
+            WebhookService::Discord => 2000,
+            WebhookService::Slack => 4000,
+        }
+    }
+
+    fn payload(self, message: &str) -> String {
+        let payload = match self {
+            WebhookService::Discord => serde_json::json!({ "content": message }),
+            WebhookService::Slack => serde_json::json!({ "text": message }),
+        };
+        payload.to_string()
+    }
+}
+
+// Posts the art as code blocks, split over as many messages as the service's length
+// limit requires. Messages are sent in order, stopping at the first failure
+pub fn post_to_webhook(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    service: WebhookService,
+    art: &str,
+) -> Result<(), ProgramError> {
+    for message in messages(art, service.message_limit()) {
+        fetcher.post_json(url, &service.payload(&message))?;
+    }
+    Ok(())
+}
+
+// Splits at line boundaries, cutting lines too long for a message of their own
+fn messages(art: &str, limit: usize) -> Vec<String> {
+    // The fence must be longer than any backtick run inside the art
+    let longest_run = art.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    let room = limit.saturating_sub(2 * fence.len() + 1).max(2);
+
+    let mut messages = Vec::new();
+    let mut body = String::new();
+    let mut body_len = 0;
+    for line in art.lines() {
+        let line: String = line.chars().take(room - 1).collect();
+        let line_len = line.chars().count() + 1;
+        if body_len + line_len > room && !body.is_empty() {
+            messages.push(format!("{fence}\n{body}{fence}"));
+            body.clear();
+            body_len = 0;
+        }
+        body.push_str(&line);
+        body.push('\n');
+        body_len += line_len;
+    }
+    if !body.is_empty() {
+        messages.push(format!("{fence}\n{body}{fence}"));
+    }
+    messages
+}